@@ -0,0 +1,378 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::deserialize_duration;
+use crate::sampler::format_timestamp;
+
+/// Resolved alerts kept for `/alerts`, newest first.
+const RESOLVED_LIMIT: usize = 100;
+/// Consecutive samples a rule's metric may be missing from before its alert
+/// is resolved as stale, e.g. after a sensor disappears or a probe is disabled.
+const STALE_AFTER: u32 = 3;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = ">=")]
+    AboveOrEqual,
+    #[serde(rename = "<")]
+    Below,
+    #[serde(rename = "<=")]
+    BelowOrEqual,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl Comparison {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Comparison::Above => ">",
+            Comparison::AboveOrEqual => ">=",
+            Comparison::Below => "<",
+            Comparison::BelowOrEqual => "<=",
+        }
+    }
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// One threshold rule, e.g. `cpu_temp > 85 for 2m, clear below 80`.
+#[derive(Deserialize, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub metric: String,
+    pub op: Comparison,
+    pub threshold: f64,
+    /// How long the condition must hold before the alert fires.
+    #[serde(rename = "for", default, deserialize_with = "deserialize_duration")]
+    pub hold: Duration,
+    /// Value the metric must cross back over before a firing alert resolves.
+    /// Defaults to `threshold`, i.e. no hysteresis.
+    pub clear: Option<f64>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+impl Rule {
+    /// Rejects a `clear` value on the breached side of the threshold, which
+    /// would keep a firing alert from ever resolving.
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(clear) = self.clear else {
+            return Ok(());
+        };
+
+        if self.breached(clear) {
+            return Err(format!(
+                "clear {} is not on the other side of {} {}",
+                clear,
+                self.op.as_str(),
+                self.threshold
+            ));
+        }
+        Ok(())
+    }
+
+    fn breached(&self, value: f64) -> bool {
+        match self.op {
+            Comparison::Above => value > self.threshold,
+            Comparison::AboveOrEqual => value >= self.threshold,
+            Comparison::Below => value < self.threshold,
+            Comparison::BelowOrEqual => value <= self.threshold,
+        }
+    }
+
+    fn cleared(&self, value: f64) -> bool {
+        let Some(clear) = self.clear else {
+            return !self.breached(value);
+        };
+
+        match self.op {
+            Comparison::Above | Comparison::AboveOrEqual => value <= clear,
+            Comparison::Below | Comparison::BelowOrEqual => value >= clear,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Pending,
+    Firing,
+    Resolved,
+}
+
+impl AlertState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertState::Pending => "pending",
+            AlertState::Firing => "firing",
+            AlertState::Resolved => "resolved",
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Alert {
+    pub rule: String,
+    pub metric: String,
+    pub severity: Severity,
+    pub state: AlertState,
+    pub op: Comparison,
+    pub threshold: f64,
+    pub value: f64,
+    pub started_at: String,
+    pub fired_at: Option<String>,
+    pub resolved_at: Option<String>,
+    /// Set when the alert resolved because its metric stopped being reported.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+#[derive(Serialize)]
+pub struct AlertsResponse {
+    pub active: Vec<Alert>,
+    pub resolved: Vec<Alert>,
+}
+
+struct RuleState {
+    rule: Rule,
+    active: Option<(Alert, SystemTime)>,
+    missed: u32,
+}
+
+/// Evaluates every rule against each newly sampled point and tracks the
+/// pending → firing → resolved lifecycle of the resulting alerts.
+pub struct AlertEngine {
+    rules: Vec<RuleState>,
+    resolved: VecDeque<Alert>,
+}
+
+pub type SharedAlerts = Arc<RwLock<AlertEngine>>;
+
+impl AlertEngine {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        AlertEngine {
+            rules: rules.into_iter().map(|rule| RuleState { rule, active: None, missed: 0 }).collect(),
+            resolved: VecDeque::new(),
+        }
+    }
+
+    /// Feeds one sample's points through the rules and returns the alerts that
+    /// changed to firing or resolved as a result.
+    pub fn observe(&mut self, at: SystemTime, points: &[(String, f64)]) -> Vec<Alert> {
+        let mut transitions = Vec::new();
+
+        for state in &mut self.rules {
+            let Some(&(_, value)) = points.iter().find(|(metric, _)| *metric == state.rule.metric) else {
+                state.missed += 1;
+                if state.missed < STALE_AFTER {
+                    continue;
+                }
+
+                match state.active.take() {
+                    Some((mut alert, _)) if alert.state == AlertState::Firing => {
+                        alert.state = AlertState::Resolved;
+                        alert.resolved_at = Some(format_timestamp(at));
+                        alert.stale = true;
+
+                        transitions.push(alert.clone());
+                        self.resolved.push_front(alert);
+                        self.resolved.truncate(RESOLVED_LIMIT);
+                    }
+                    _ => {}
+                }
+                continue;
+            };
+            state.missed = 0;
+            let rule = &state.rule;
+
+            match &mut state.active {
+                None if rule.breached(value) => {
+                    let alert = Alert {
+                        rule: rule.name.clone(),
+                        metric: rule.metric.clone(),
+                        severity: rule.severity,
+                        state: AlertState::Pending,
+                        op: rule.op,
+                        threshold: rule.threshold,
+                        value,
+                        started_at: format_timestamp(at),
+                        fired_at: None,
+                        resolved_at: None,
+                        stale: false,
+                    };
+                    state.active = Some((alert, at));
+                }
+                None => {}
+                Some((alert, _)) if alert.state == AlertState::Pending && !rule.breached(value) => {
+                    state.active = None;
+                    continue;
+                }
+                Some((alert, _)) if alert.state == AlertState::Firing && rule.cleared(value) => {
+                    alert.state = AlertState::Resolved;
+                    alert.value = value;
+                    alert.resolved_at = Some(format_timestamp(at));
+
+                    transitions.push(alert.clone());
+                    self.resolved.push_front(alert.clone());
+                    self.resolved.truncate(RESOLVED_LIMIT);
+                    state.active = None;
+                    continue;
+                }
+                Some((alert, _)) => alert.value = value,
+            }
+
+            if let Some((alert, since)) = &mut state.active
+                && alert.state == AlertState::Pending
+                && at.duration_since(*since).unwrap_or_default() >= rule.hold
+            {
+                alert.state = AlertState::Firing;
+                alert.fired_at = Some(format_timestamp(at));
+                transitions.push(alert.clone());
+            }
+        }
+
+        transitions
+    }
+
+    pub fn response(&self) -> AlertsResponse {
+        AlertsResponse {
+            active: self.rules.iter().filter_map(|s| s.active.as_ref().map(|(a, _)| a.clone())).collect(),
+            resolved: self.resolved.iter().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(op: &str, threshold: f64, hold: &str, clear: Option<f64>) -> Rule {
+        serde_json::from_value(serde_json::json!({
+            "name": "cpu_hot",
+            "metric": "cpu_temp",
+            "op": op,
+            "threshold": threshold,
+            "for": hold,
+            "clear": clear,
+        }))
+        .unwrap()
+    }
+
+    /// Feeds `values` one sample apart, starting at 1000s past the epoch, and
+    /// returns the transitions of each sample as state names.
+    fn run(engine: &mut AlertEngine, start: u64, values: &[Option<f64>]) -> Vec<Vec<&'static str>> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let at = SystemTime::UNIX_EPOCH + Duration::from_secs(1000 + start + i as u64 * 10);
+                let points: Vec<(String, f64)> = value.map(|v| ("cpu_temp".to_string(), v)).into_iter().collect();
+                engine.observe(at, &points).iter().map(|a| a.state.as_str()).collect()
+            })
+            .collect()
+    }
+
+    fn active_state(engine: &AlertEngine) -> Option<&'static str> {
+        engine.response().active.first().map(|a| a.state.as_str())
+    }
+
+    #[test]
+    fn fires_once_the_condition_holds() {
+        let mut engine = AlertEngine::new(vec![rule(">", 85.0, "20s", None)]);
+
+        let transitions = run(&mut engine, 0, &[Some(90.0), Some(91.0)]);
+        assert_eq!(transitions, [Vec::<&str>::new(), vec![]]);
+        assert_eq!(active_state(&engine), Some("pending"));
+
+        assert_eq!(run(&mut engine, 20, &[Some(92.0)]), [["firing"]]);
+        let alert = &engine.response().active[0];
+        assert_eq!(alert.value, 92.0);
+        assert!(alert.fired_at.is_some());
+    }
+
+    #[test]
+    fn fires_immediately_without_a_hold() {
+        let mut engine = AlertEngine::new(vec![rule(">=", 85.0, "0s", None)]);
+        assert_eq!(run(&mut engine, 0, &[Some(80.0), Some(85.0)]), [vec![], vec!["firing"]]);
+    }
+
+    #[test]
+    fn recovering_during_the_hold_resets_pending() {
+        let mut engine = AlertEngine::new(vec![rule(">", 85.0, "20s", None)]);
+
+        run(&mut engine, 0, &[Some(90.0), Some(80.0)]);
+        assert_eq!(active_state(&engine), None);
+
+        // The hold restarts from the next breach rather than the first one.
+        assert_eq!(run(&mut engine, 20, &[Some(90.0), Some(90.0)]), [Vec::<&str>::new(), vec![]]);
+        assert_eq!(run(&mut engine, 40, &[Some(90.0)]), [["firing"]]);
+    }
+
+    #[test]
+    fn resolves_once_the_value_crosses_clear() {
+        let mut engine = AlertEngine::new(vec![rule(">", 85.0, "0s", Some(80.0))]);
+
+        let transitions = run(&mut engine, 0, &[Some(90.0), Some(84.0), Some(81.0), Some(80.0)]);
+        assert_eq!(transitions, [vec!["firing"], vec![], vec![], vec!["resolved"]]);
+
+        let response = engine.response();
+        assert!(response.active.is_empty());
+        assert_eq!(response.resolved[0].value, 80.0);
+        assert!(response.resolved[0].resolved_at.is_some());
+        assert!(!response.resolved[0].stale);
+    }
+
+    #[test]
+    fn resolves_below_threshold_without_clear() {
+        let mut engine = AlertEngine::new(vec![rule("<", 10.0, "0s", None)]);
+        assert_eq!(run(&mut engine, 0, &[Some(5.0), Some(10.0)]), [vec!["firing"], vec!["resolved"]]);
+    }
+
+    #[test]
+    fn resolves_as_stale_when_the_metric_disappears() {
+        let mut engine = AlertEngine::new(vec![rule(">", 85.0, "0s", None)]);
+
+        let transitions = run(&mut engine, 0, &[Some(90.0), None, None, None]);
+        assert_eq!(transitions, [vec!["firing"], vec![], vec![], vec!["resolved"]]);
+        assert!(engine.response().resolved[0].stale);
+    }
+
+    #[test]
+    fn a_returning_metric_is_not_stale() {
+        let mut engine = AlertEngine::new(vec![rule(">", 85.0, "0s", None)]);
+
+        let transitions = run(&mut engine, 0, &[Some(90.0), None, None, Some(90.0), None, None]);
+        assert!(transitions[1..].iter().all(Vec::is_empty));
+        assert_eq!(active_state(&engine), Some("firing"));
+    }
+
+    #[test]
+    fn rejects_clear_on_the_breached_side() {
+        assert!(rule(">", 85.0, "0s", Some(80.0)).validate().is_ok());
+        assert!(rule(">", 85.0, "0s", Some(90.0)).validate().is_err());
+        assert!(rule(">=", 85.0, "0s", Some(85.0)).validate().is_err());
+        assert!(rule("<", 10.0, "0s", Some(15.0)).validate().is_ok());
+        assert!(rule("<=", 10.0, "0s", Some(5.0)).validate().is_err());
+        assert!(rule("<", 10.0, "0s", None).validate().is_ok());
+    }
+}
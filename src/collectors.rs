@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{System, MINIMUM_CPU_UPDATE_INTERVAL};
+
+use crate::hwmon::{self, Chip};
+use crate::models::{CpuAverages, ServerData, TempData};
+use crate::roles::RoleMap;
+
+const CPU_HISTORY_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Default)]
+pub struct UsageSample {
+    pub cpu_percentage: f32,
+    pub cpu_per_core: Vec<f32>,
+    pub cpu_average: CpuAverages,
+    pub used_memory: u64,
+    pub total_memory: u64,
+}
+
+impl UsageSample {
+    pub fn memory_gib(&self) -> f32 {
+        self.used_memory as f32 / (1024.0 * 1024.0 * 1024.0)
+    }
+
+    pub fn total_memory_gib(&self) -> f32 {
+        self.total_memory as f32 / (1024.0 * 1024.0 * 1024.0)
+    }
+
+    pub fn memory_percentage(&self) -> f32 {
+        (self.used_memory as f32 / self.total_memory as f32) * 100.0
+    }
+}
+
+struct CpuReading {
+    at: Instant,
+    elapsed: Duration,
+    usage: f32,
+}
+
+/// Owns a long-lived `System` so CPU usage is computed from the delta between
+/// consecutive refreshes, which sysinfo needs to report anything meaningful.
+pub struct UsageCollector {
+    sys: System,
+    last_refresh: Instant,
+    readings: VecDeque<CpuReading>,
+}
+
+impl UsageCollector {
+    pub fn new() -> Self {
+        let mut sys = System::new();
+        sys.refresh_cpu();
+        let last_refresh = Instant::now();
+        thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+
+        UsageCollector { sys, last_refresh, readings: VecDeque::new() }
+    }
+
+    pub fn sample(&mut self) -> UsageSample {
+        self.sys.refresh_cpu();
+        self.sys.refresh_memory();
+
+        let now = Instant::now();
+        let cpu_percentage = self.sys.global_cpu_info().cpu_usage();
+        self.readings.push_back(CpuReading {
+            at: now,
+            elapsed: now - self.last_refresh,
+            usage: cpu_percentage,
+        });
+        self.last_refresh = now;
+
+        while self.readings.front().is_some_and(|r| now - r.at > CPU_HISTORY_WINDOW) {
+            self.readings.pop_front();
+        }
+
+        UsageSample {
+            cpu_percentage,
+            cpu_per_core: self.sys.cpus().iter().map(|c| c.cpu_usage()).collect(),
+            cpu_average: CpuAverages {
+                last_1s: self.average_over(now, Duration::from_secs(1)),
+                last_10s: self.average_over(now, Duration::from_secs(10)),
+                last_60s: self.average_over(now, CPU_HISTORY_WINDOW),
+            },
+            used_memory: self.sys.used_memory(),
+            total_memory: self.sys.total_memory(),
+        }
+    }
+
+    /// Time-weighted mean of the readings taken within `window` of `now`.
+    fn average_over(&self, now: Instant, window: Duration) -> f32 {
+        let (weighted, total) = self.readings.iter()
+            .filter(|r| now - r.at <= window)
+            .fold((0.0, 0.0), |(weighted, total), r| {
+                let secs = r.elapsed.as_secs_f32();
+                (weighted + r.usage * secs, total + secs)
+            });
+
+        if total > 0.0 { weighted / total } else { 0.0 }
+    }
+}
+
+pub fn get_server_data() -> ServerData {
+    let mut sys = System::new();
+    sys.refresh_cpu();
+
+    ServerData {
+        server_name: System::host_name(),
+        server_cpu: sys.cpus().first().map(|c| c.brand().to_string()).unwrap_or_default(),
+        server_os: System::name(),
+    }
+}
+
+/// Resolves the configured temperature roles against the sensor inventory.
+pub fn get_all_temps(chips: &[Chip], roles: &RoleMap) -> TempData {
+    let sensors = hwmon::temperatures(chips);
+    let mut temps = TempData { sensors, ..TempData::default() };
+
+    for (role, value) in roles.resolve(&temps.sensors) {
+        match role {
+            "motherboard_temp" => temps.motherboard_temp = value,
+            "cpu_temp" => temps.cpu_temp = value,
+            "gpu_temp" => temps.gpu_temp = value,
+            _ => {
+                temps.extra.insert(role.to_string(), value);
+            }
+        }
+    }
+
+    temps
+}
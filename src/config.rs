@@ -0,0 +1,137 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+
+/// Reads a humantime duration (`30s`, `5m`, `30days`) from the environment,
+/// falling back to `default` when the variable is unset or malformed.
+pub fn duration_from_env(key: &str, default: &str) -> Duration {
+    let value = env::var(key).unwrap_or_else(|_| default.to_string());
+    humantime::parse_duration(&value).unwrap_or_else(|e| {
+        eprintln!("Invalid {} {:?} ({}), using {}", key, value, e, default);
+        humantime::parse_duration(default).expect("valid default duration")
+    })
+}
+
+/// Loads a JSON config file named by the environment variable `key`.
+/// Returns `None` when the variable is unset; read or parse failures are
+/// reported and also yield `None`.
+pub fn json_file_from_env<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let path = env::var(key).ok().filter(|p| !p.is_empty())?;
+
+    let parsed = fs::read_to_string(Path::new(&path))
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()));
+
+    match parsed {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("Failed to load {} from {}: {}", key, path, e);
+            None
+        }
+    }
+}
+
+/// Serde helper for humantime duration strings in JSON config files.
+pub fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    humantime::parse_duration(&text).map_err(serde::de::Error::custom)
+}
+
+/// Describes a failed HTTP request without its URL, which may carry
+/// credentials in the userinfo, path or query (webhook secrets, `?token=`).
+/// Only the host is kept so the failing endpoint can still be told apart.
+pub fn redacted_error(error: &ureq::Error) -> String {
+    let transport = match error {
+        ureq::Error::Status(code, _) => return format!("HTTP {}", code),
+        ureq::Error::Transport(transport) => transport,
+    };
+
+    let mut text = match transport.url().and_then(|url| url.host_str()) {
+        Some(host) => format!("{}: {}", host, transport.kind()),
+        None => transport.kind().to_string(),
+    };
+    if let Some(message) = transport.message() {
+        let message = match transport.url() {
+            Some(url) => message.replace(url.as_str(), "<url>"),
+            None => message.to_string(),
+        };
+        text.push_str(&format!(": {}", message));
+    }
+    if let Some(source) = std::error::Error::source(transport) {
+        text.push_str(&format!(": {}", source));
+    }
+    text
+}
+
+/// Case-insensitive glob match supporting `*` (any run) and `?` (one char).
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wildcard_match;
+
+    #[test]
+    fn matches_literals_case_insensitively() {
+        assert!(wildcard_match("nvme0n1", "nvme0n1"));
+        assert!(wildcard_match("SDA", "sda"));
+        assert!(!wildcard_match("sda", "sdb"));
+        assert!(!wildcard_match("sda", "sda1"));
+        assert!(wildcard_match("", ""));
+        assert!(!wildcard_match("", "sda"));
+    }
+
+    #[test]
+    fn question_mark_matches_exactly_one_char() {
+        assert!(wildcard_match("sd?", "sdb"));
+        assert!(!wildcard_match("sd?", "sd"));
+        assert!(!wildcard_match("sd?", "sdaa"));
+        assert!(wildcard_match("cpu?thermal", "cpu-thermal"));
+    }
+
+    #[test]
+    fn star_matches_any_run_with_backtracking() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("nvme*", "nvme"));
+        assert!(wildcard_match("nvme*", "nvme0n1p2"));
+        assert!(wildcard_match("*asus*", "asus_wmi_sensors"));
+        assert!(wildcard_match("loop*p*", "loop12p3p1"));
+        assert!(wildcard_match("*a*b", "xaxab"));
+        assert!(!wildcard_match("*a*b", "xaxba"));
+        assert!(wildcard_match("**", "anything"));
+        assert!(!wildcard_match("dm-*", "md0"));
+    }
+
+    #[test]
+    fn handles_multibyte_characters() {
+        assert!(wildcard_match("temp?", "tempé"));
+        assert!(wildcard_match("*ü*", "Düsseldorf"));
+    }
+}
@@ -0,0 +1,621 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{deserialize_duration, redacted_error};
+use crate::publicip::PublicIp;
+use crate::sampler::format_timestamp;
+
+/// DynDNS2 return codes that come back with HTTP 200 but mean failure.
+const DYNDNS_ERRORS: [&str; 9] =
+    ["badauth", "!donator", "notfqdn", "nohost", "numhost", "abuse", "badagent", "dnserr", "911"];
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl RecordFamily {
+    fn record_type(self) -> u16 {
+        match self {
+            RecordFamily::Ipv4 => 1,
+            RecordFamily::Ipv6 => 28,
+        }
+    }
+
+    fn address(self, ip: &PublicIp) -> Option<&String> {
+        match self {
+            RecordFamily::Ipv4 => ip.ipv4.as_ref(),
+            RecordFamily::Ipv6 => ip.ipv6.as_ref(),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            RecordFamily::Ipv4 => "ipv4",
+            RecordFamily::Ipv6 => "ipv6",
+        }
+    }
+}
+
+fn default_families() -> Vec<RecordFamily> {
+    vec![RecordFamily::Ipv4, RecordFamily::Ipv6]
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_ttl() -> u32 {
+    300
+}
+
+fn default_algorithm() -> String {
+    "hmac-sha256".to_string()
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// A transaction signature key shared with the DNS server.
+#[derive(Deserialize, Clone)]
+pub struct TsigKey {
+    pub name: String,
+    /// Base64 secret, as in a BIND `key` statement.
+    pub secret: String,
+    #[serde(default = "default_algorithm")]
+    pub algorithm: String,
+}
+
+/// RFC 2136 dynamic update sent over UDP to the zone's primary server.
+#[derive(Deserialize, Clone)]
+pub struct Rfc2136 {
+    /// `host` or `host:port`; port 53 is assumed when omitted.
+    pub server: String,
+    pub zone: String,
+    #[serde(default = "default_ttl")]
+    pub ttl: u32,
+    #[serde(default)]
+    pub tsig: Option<TsigKey>,
+}
+
+/// DynDNS-style HTTP API. `{hostname}`, `{ip}` and `{family}` in the URL are
+/// substituted for each update.
+#[derive(Deserialize, Clone)]
+pub struct HttpApi {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Protocol {
+    Rfc2136(Rfc2136),
+    Http(HttpApi),
+}
+
+impl Protocol {
+    fn kind(&self) -> &'static str {
+        match self {
+            Protocol::Rfc2136(_) => "rfc2136",
+            Protocol::Http(_) => "http",
+        }
+    }
+
+    fn update(&self, record: &Record, timeout: Duration) -> Result<String, String> {
+        match self {
+            Protocol::Rfc2136(rfc2136) => rfc2136.update(record, timeout),
+            Protocol::Http(api) => api.update(record, timeout),
+        }
+    }
+}
+
+/// The record one update attempt sets.
+struct Record<'a> {
+    hostname: &'a str,
+    family: RecordFamily,
+    address: &'a str,
+}
+
+/// An updater loaded from `DDNS_FILE`.
+#[derive(Deserialize, Clone)]
+pub struct Updater {
+    pub name: String,
+    pub hostnames: Vec<String>,
+    #[serde(default = "default_families")]
+    pub families: Vec<RecordFamily>,
+    #[serde(default = "default_timeout", deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+    #[serde(flatten)]
+    pub protocol: Protocol,
+}
+
+/// Outcome of the latest update attempt for one hostname and address family.
+#[derive(Serialize, Clone)]
+pub struct RecordStatus {
+    pub updater: String,
+    pub kind: &'static str,
+    pub hostname: String,
+    pub family: RecordFamily,
+    pub address: String,
+    pub success: bool,
+    pub attempted_at: String,
+    /// When this record was last updated successfully, possibly with an
+    /// older address.
+    pub updated_at: Option<String>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+pub type SharedDdnsStatus = Arc<RwLock<Vec<RecordStatus>>>;
+
+/// Pushes the public addresses to DNS. Each record is updated when its
+/// address differs from the last one pushed successfully, so failures are
+/// retried on every lookup and unchanged addresses cost nothing.
+pub struct Ddns {
+    updaters: Vec<Updater>,
+    status: SharedDdnsStatus,
+}
+
+impl Ddns {
+    pub fn new(updaters: Vec<Updater>) -> Self {
+        Ddns { updaters, status: SharedDdnsStatus::default() }
+    }
+
+    pub fn status(&self) -> SharedDdnsStatus {
+        Arc::clone(&self.status)
+    }
+
+    pub fn update(&self, ip: &PublicIp) {
+        for updater in &self.updaters {
+            for &family in &updater.families {
+                let Some(address) = family.address(ip) else { continue };
+
+                for hostname in &updater.hostnames {
+                    let (previous, up_to_date) = {
+                        let statuses = self.status.read().unwrap();
+                        let previous = statuses.iter().position(|s| {
+                            s.updater == updater.name && s.hostname == *hostname && s.family == family
+                        });
+                        (previous, previous.is_some_and(|i| statuses[i].success && statuses[i].address == *address))
+                    };
+                    if up_to_date {
+                        continue;
+                    }
+
+                    let record = Record { hostname, family, address };
+                    let outcome = updater.protocol.update(&record, updater.timeout);
+
+                    let now = format_timestamp(SystemTime::now());
+                    match &outcome {
+                        Ok(_) => println!("🌍 DDNS {} updated {} to {}", updater.name, hostname, address),
+                        Err(e) => eprintln!("DDNS {} failed to update {}: {}", updater.name, hostname, e),
+                    }
+
+                    let mut statuses = self.status.write().unwrap();
+                    let updated_at = match (&outcome, previous) {
+                        (Ok(_), _) => Some(now.clone()),
+                        (Err(_), Some(i)) => statuses[i].updated_at.clone(),
+                        (Err(_), None) => None,
+                    };
+                    let status = RecordStatus {
+                        updater: updater.name.clone(),
+                        kind: updater.protocol.kind(),
+                        hostname: hostname.clone(),
+                        family,
+                        address: address.clone(),
+                        success: outcome.is_ok(),
+                        attempted_at: now,
+                        updated_at,
+                        response: outcome.as_ref().ok().cloned(),
+                        error: outcome.err(),
+                    };
+                    match previous {
+                        Some(i) => statuses[i] = status,
+                        None => statuses.push(status),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl HttpApi {
+    fn update(&self, record: &Record, timeout: Duration) -> Result<String, String> {
+        let url = self
+            .url
+            .replace("{hostname}", record.hostname)
+            .replace("{ip}", record.address)
+            .replace("{family}", record.family.as_str());
+
+        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        let mut request = agent.request(&self.method, &url).set("User-Agent", "status_server");
+        if let Some(username) = &self.username {
+            let credentials = BASE64.encode(format!("{}:{}", username, self.password.as_deref().unwrap_or_default()));
+            request = request.set("Authorization", &format!("Basic {}", credentials));
+        }
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+
+        let body = match request.call() {
+            Ok(response) => response.into_string().map_err(|e| e.to_string())?,
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                return Err(format!("HTTP {}: {}", code, body.trim()));
+            }
+            Err(e) => return Err(redacted_error(&e)),
+        };
+
+        let body = body.trim().to_string();
+        match DYNDNS_ERRORS.iter().any(|code| body.starts_with(code)) {
+            true => Err(body),
+            false => Ok(body),
+        }
+    }
+}
+
+impl Rfc2136 {
+    fn update(&self, record: &Record, timeout: Duration) -> Result<String, String> {
+        let rdata = match record.address.parse::<IpAddr>().map_err(|e| e.to_string())? {
+            IpAddr::V4(v4) => v4.octets().to_vec(),
+            IpAddr::V6(v6) => v6.octets().to_vec(),
+        };
+
+        let mut id = [0u8; 2];
+        SystemRandom::new().fill(&mut id).map_err(|_| "no randomness available".to_string())?;
+        let id = u16::from_be_bytes(id);
+
+        // Header: opcode UPDATE, one zone, no prerequisites, two updates.
+        let mut message = Vec::with_capacity(512);
+        put_u16(&mut message, id);
+        put_u16(&mut message, 5 << 11);
+        for count in [1, 0, 2, 0] {
+            put_u16(&mut message, count);
+        }
+
+        // Zone section.
+        put_name(&mut message, &self.zone)?;
+        put_u16(&mut message, 6); // SOA
+        put_u16(&mut message, 1); // IN
+
+        // Delete the existing RRset of this type, then add the new address.
+        put_name(&mut message, record.hostname)?;
+        put_u16(&mut message, record.family.record_type());
+        put_u16(&mut message, 255); // ANY
+        put_u32(&mut message, 0);
+        put_u16(&mut message, 0);
+
+        put_name(&mut message, record.hostname)?;
+        put_u16(&mut message, record.family.record_type());
+        put_u16(&mut message, 1);
+        put_u32(&mut message, self.ttl);
+        put_u16(&mut message, rdata.len() as u16);
+        message.extend_from_slice(&rdata);
+
+        if let Some(key) = &self.tsig {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            sign(&mut message, id, key, now)?;
+        }
+
+        let addr = self
+            .server
+            .to_socket_addrs()
+            .or_else(|_| (self.server.as_str(), 53).to_socket_addrs())
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("{} did not resolve", self.server))?;
+
+        let socket = UdpSocket::bind(if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).map_err(|e| e.to_string())?;
+        socket.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+        socket.connect(addr).map_err(|e| e.to_string())?;
+        socket.send(&message).map_err(|e| e.to_string())?;
+
+        let mut reply = [0u8; 4096];
+        loop {
+            let len = socket.recv(&mut reply).map_err(|e| e.to_string())?;
+            if len < 12 || u16::from_be_bytes([reply[0], reply[1]]) != id {
+                continue;
+            }
+
+            return match reply[3] & 0x0f {
+                0 => Ok("NOERROR".to_string()),
+                rcode => Err(rcode_name(rcode).to_string()),
+            };
+        }
+    }
+}
+
+/// Appends a TSIG record (RFC 8945) covering `message`, signed at the Unix
+/// time `signed_at`. The server's signed reply is not verified; only its
+/// response code is checked.
+fn sign(message: &mut Vec<u8>, id: u16, key: &TsigKey, signed_at: u64) -> Result<(), String> {
+    let algorithm = match key.algorithm.trim_end_matches('.').to_lowercase().as_str() {
+        "hmac-sha256" => hmac::HMAC_SHA256,
+        "hmac-sha384" => hmac::HMAC_SHA384,
+        "hmac-sha512" => hmac::HMAC_SHA512,
+        other => return Err(format!("unsupported TSIG algorithm {}", other)),
+    };
+    let secret = BASE64.decode(key.secret.trim()).map_err(|e| format!("invalid TSIG secret: {}", e))?;
+    let fudge: u16 = 300;
+
+    let mut key_name = Vec::new();
+    put_name(&mut key_name, &key.name.to_lowercase())?;
+    let mut algorithm_name = Vec::new();
+    put_name(&mut algorithm_name, &key.algorithm.to_lowercase())?;
+
+    let mut timers = Vec::new();
+    timers.extend_from_slice(&signed_at.to_be_bytes()[2..]);
+    put_u16(&mut timers, fudge);
+
+    let mut digest = message.clone();
+    digest.extend_from_slice(&key_name);
+    put_u16(&mut digest, 255);
+    put_u32(&mut digest, 0);
+    digest.extend_from_slice(&algorithm_name);
+    digest.extend_from_slice(&timers);
+    put_u16(&mut digest, 0); // error
+    put_u16(&mut digest, 0); // other len
+
+    let mac = hmac::sign(&hmac::Key::new(algorithm, &secret), &digest);
+    let mac = mac.as_ref();
+
+    let mut rdata = algorithm_name;
+    rdata.extend_from_slice(&timers);
+    put_u16(&mut rdata, mac.len() as u16);
+    rdata.extend_from_slice(mac);
+    put_u16(&mut rdata, id);
+    put_u16(&mut rdata, 0);
+    put_u16(&mut rdata, 0);
+
+    message.extend_from_slice(&key_name);
+    put_u16(message, 250); // TSIG
+    put_u16(message, 255);
+    put_u32(message, 0);
+    put_u16(message, rdata.len() as u16);
+    message.extend_from_slice(&rdata);
+
+    let additional = u16::from_be_bytes([message[10], message[11]]) + 1;
+    message[10..12].copy_from_slice(&additional.to_be_bytes());
+    Ok(())
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Writes `name` in uncompressed wire format.
+fn put_name(buf: &mut Vec<u8>, name: &str) -> Result<(), String> {
+    for label in name.trim_end_matches('.').split('.').filter(|l| !l.is_empty()) {
+        if label.len() > 63 {
+            return Err(format!("label {:?} in {} is longer than 63 bytes", label, name));
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    Ok(())
+}
+
+fn rcode_name(rcode: u8) -> &'static str {
+    match rcode {
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        6 => "YXDOMAIN",
+        7 => "YXRRSET",
+        8 => "NXRRSET",
+        9 => "NOTAUTH",
+        10 => "NOTZONE",
+        _ => "unknown error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn record<'a>(hostname: &'a str, family: RecordFamily, address: &'a str) -> Record<'a> {
+        Record { hostname, family, address }
+    }
+
+    fn read_name(message: &[u8], pos: &mut usize) -> String {
+        let mut labels = Vec::new();
+        while message[*pos] != 0 {
+            let len = message[*pos] as usize;
+            labels.push(String::from_utf8(message[*pos + 1..*pos + 1 + len].to_vec()).unwrap());
+            *pos += 1 + len;
+        }
+        *pos += 1;
+        labels.join(".")
+    }
+
+    fn read_u16(message: &[u8], pos: &mut usize) -> u16 {
+        *pos += 2;
+        u16::from_be_bytes([message[*pos - 2], message[*pos - 1]])
+    }
+
+    fn read_u32(message: &[u8], pos: &mut usize) -> u32 {
+        *pos += 4;
+        u32::from_be_bytes(message[*pos - 4..*pos].try_into().unwrap())
+    }
+
+    /// Answers one DNS message with `rcode` and hands back what it received.
+    fn dns_stand_in(rcode: u8) -> (String, thread::JoinHandle<Vec<u8>>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            let (len, peer) = socket.recv_from(&mut buf).unwrap();
+            let mut reply = buf[..12].to_vec();
+            reply[2] |= 0x80;
+            reply[3] = rcode;
+            reply[4..12].fill(0);
+            socket.send_to(&reply, peer).unwrap();
+            buf[..len].to_vec()
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn rfc2136_sends_zone_and_replacement_records() {
+        let (server, stand_in) = dns_stand_in(0);
+        let rfc2136 = Rfc2136 { server, zone: "example.com".to_string(), ttl: 120, tsig: None };
+
+        let outcome = rfc2136.update(&record("home.example.com", RecordFamily::Ipv4, "203.0.113.7"), Duration::from_secs(2));
+        assert_eq!(outcome, Ok("NOERROR".to_string()));
+
+        let message = stand_in.join().unwrap();
+        let mut pos = 2;
+        assert_eq!(read_u16(&message, &mut pos) >> 11 & 0x0f, 5, "opcode UPDATE");
+        assert_eq!([0, 0, 0, 0].map(|_| read_u16(&message, &mut pos)), [1, 0, 2, 0]);
+
+        assert_eq!(read_name(&message, &mut pos), "example.com");
+        assert_eq!((read_u16(&message, &mut pos), read_u16(&message, &mut pos)), (6, 1), "SOA IN");
+
+        // Delete the A RRset...
+        assert_eq!(read_name(&message, &mut pos), "home.example.com");
+        assert_eq!((read_u16(&message, &mut pos), read_u16(&message, &mut pos)), (1, 255));
+        assert_eq!((read_u32(&message, &mut pos), read_u16(&message, &mut pos)), (0, 0));
+
+        // ...and add the new address.
+        assert_eq!(read_name(&message, &mut pos), "home.example.com");
+        assert_eq!((read_u16(&message, &mut pos), read_u16(&message, &mut pos)), (1, 1));
+        assert_eq!((read_u32(&message, &mut pos), read_u16(&message, &mut pos)), (120, 4));
+        assert_eq!(&message[pos..], &[203, 0, 113, 7]);
+    }
+
+    #[test]
+    fn rfc2136_reports_error_rcodes() {
+        let (server, stand_in) = dns_stand_in(5);
+        let rfc2136 = Rfc2136 { server, zone: "example.com".to_string(), ttl: 300, tsig: None };
+
+        let outcome = rfc2136.update(&record("home.example.com", RecordFamily::Ipv6, "2001:db8::1"), Duration::from_secs(2));
+        assert_eq!(outcome, Err("REFUSED".to_string()));
+
+        let message = stand_in.join().unwrap();
+        assert_eq!(&message[message.len() - 16..], "2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap().octets());
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    /// RFC 8945 has no test vectors, so this one comes from another TSIG
+    /// implementation: hickory-proto 0.24 signing an UPDATE for
+    /// home.example.com A 203.0.113.7 in example.com with the key below at
+    /// 1700000000 (fudge 300). It compresses names, which the MAC covers too.
+    #[test]
+    fn tsig_matches_an_independent_signer() {
+        let key = TsigKey {
+            name: "update-key".to_string(),
+            secret: BASE64.encode("secret-key-shared-with-bind"),
+            algorithm: "hmac-sha256".to_string(),
+        };
+        let mut message = from_hex(
+            "123428000001000000020000076578616d706c6503636f6d000006000104686f6d65c00c000100ff00000000\
+             0000c01d00010001000000780004cb007107",
+        );
+
+        sign(&mut message, 0x1234, &key, 1_700_000_000).unwrap();
+
+        let expected = from_hex(
+            "123428000001000000020001076578616d706c6503636f6d000006000104686f6d65c00c000100ff00000000\
+             0000c01d00010001000000780004cb0071070a7570646174652d6b65790000fa00ff00000000003d0b686d61\
+             632d7368613235360000006553f100012c0020ae422f6dc575ee5cb746a805a391cb74cd57e7d32d485d29d5\
+             5edaa16cb95c72123400000000",
+        );
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn tsig_rejects_unknown_algorithms() {
+        let key = TsigKey { name: "k".to_string(), secret: "AAAA".to_string(), algorithm: "hmac-md5".to_string() };
+        assert!(sign(&mut vec![0; 12], 1, &key, 0).is_err());
+    }
+
+    /// Serves one HTTP request per canned body and returns each request head.
+    fn http_stand_in(bodies: &'static [&'static str]) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            bodies
+                .iter()
+                .map(|body| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut head = String::new();
+                    while reader.read_line(&mut head).unwrap() > 2 {}
+                    write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+                        .unwrap();
+                    head
+                })
+                .collect()
+        });
+        (base, handle)
+    }
+
+    #[test]
+    fn http_api_errors_leave_credentials_out() {
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let api = HttpApi {
+            url: format!("https://user:hunter2@{}/update?domains={{hostname}}&token=SECRET-TOKEN", closed),
+            method: "GET".to_string(),
+            username: None,
+            password: None,
+            headers: HashMap::new(),
+        };
+
+        let error = api.update(&record("home", RecordFamily::Ipv4, "203.0.113.7"), Duration::from_secs(2)).unwrap_err();
+        assert!(error.contains("127.0.0.1"), "{}", error);
+        assert!(!error.contains("SECRET") && !error.contains("hunter2"), "{}", error);
+    }
+
+    #[test]
+    fn http_api_maps_dyndns_replies() {
+        let (base, stand_in) = http_stand_in(&["good 203.0.113.7", "nochg 203.0.113.7", "badauth"]);
+        let api = HttpApi {
+            url: format!("{}/nic/update?hostname={{hostname}}&myip={{ip}}&type={{family}}", base),
+            method: "GET".to_string(),
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            headers: HashMap::new(),
+        };
+        let home = record("home.example.com", RecordFamily::Ipv4, "203.0.113.7");
+
+        assert_eq!(api.update(&home, Duration::from_secs(2)), Ok("good 203.0.113.7".to_string()));
+        assert_eq!(api.update(&home, Duration::from_secs(2)), Ok("nochg 203.0.113.7".to_string()));
+        assert_eq!(api.update(&home, Duration::from_secs(2)), Err("badauth".to_string()));
+
+        let requests = stand_in.join().unwrap();
+        assert!(requests[0].starts_with("GET /nic/update?hostname=home.example.com&myip=203.0.113.7&type=ipv4 HTTP/1.1"));
+        // "user:pass" in base64.
+        assert!(requests.iter().all(|r| r.contains("Authorization: Basic dXNlcjpwYXNz\r\n")));
+    }
+}
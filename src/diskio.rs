@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::config::wildcard_match;
+
+/// `/proc/diskstats` counts sectors in 512-byte units regardless of the
+/// device's real sector size.
+const SECTOR_BYTES: u64 = 512;
+const DEFAULT_EXCLUDED_DEVICES: &str = "loop*,ram*,zram*";
+
+#[derive(Clone, Copy)]
+struct Counters {
+    reads: u64,
+    read_sectors: u64,
+    read_ms: u64,
+    writes: u64,
+    write_sectors: u64,
+    write_ms: u64,
+    io_ms: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DiskIo {
+    pub device: String,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    pub read_iops: f64,
+    pub write_iops: f64,
+    /// Mean time each completed request spent queued and in service.
+    pub await_ms: f64,
+    /// Share of wall time the device had at least one request in flight.
+    pub utilization_percentage: f64,
+    pub read_bytes_total: u64,
+    pub write_bytes_total: u64,
+    pub reads_total: u64,
+    pub writes_total: u64,
+}
+
+/// Turns consecutive `/proc/diskstats` readings into per-device rates.
+pub struct DiskIoCollector {
+    proc_root: PathBuf,
+    exclude: Vec<String>,
+    previous: Option<(Instant, HashMap<String, Counters>)>,
+}
+
+impl DiskIoCollector {
+    pub fn from_env() -> Self {
+        let exclude = env::var("DISKIO_DEVICES_EXCLUDE").unwrap_or_else(|_| DEFAULT_EXCLUDED_DEVICES.to_string());
+
+        DiskIoCollector {
+            proc_root: PathBuf::from(env::var("PROC_ROOT").unwrap_or_else(|_| "/proc".to_string())),
+            exclude: exclude.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect(),
+            previous: None,
+        }
+    }
+
+    /// Rates since the previous call. The first call only primes the
+    /// counters, so every rate is zero until the second sample.
+    pub fn sample(&mut self) -> Vec<DiskIo> {
+        let now = Instant::now();
+        let current = self.read_counters();
+        let previous = self.previous.replace((now, current.clone()));
+
+        let mut devices: Vec<DiskIo> = current.into_iter()
+            .map(|(device, counters)| {
+                let before = previous.as_ref().and_then(|(at, prev)| Some((*at, *prev.get(&device)?)));
+                rates(device, counters, before, now)
+            })
+            .collect();
+        devices.sort_by(|a, b| a.device.cmp(&b.device));
+        devices
+    }
+
+    fn read_counters(&self) -> HashMap<String, Counters> {
+        let text = fs::read_to_string(self.proc_root.join("diskstats")).unwrap_or_default();
+        parse_diskstats(&text, &self.exclude)
+    }
+}
+
+/// Counters for every whole device in `/proc/diskstats`. Partitions are
+/// skipped: their I/O is already counted by the disk they belong to.
+fn parse_diskstats(text: &str, exclude: &[String]) -> HashMap<String, Counters> {
+    let rows: Vec<Vec<&str>> = text.lines()
+        .map(|line| line.split_whitespace().collect())
+        .filter(|fields: &Vec<&str>| fields.len() >= 14)
+        .collect();
+    let devices: Vec<&str> = rows.iter().map(|fields| fields[2]).collect();
+
+    rows.iter()
+        .filter_map(|fields| {
+            let device = fields[2];
+            if exclude.iter().any(|p| wildcard_match(p, device)) || is_partition(device, &devices) {
+                return None;
+            }
+
+            let n = |i: usize| fields[i].parse::<u64>().unwrap_or(0);
+            Some((device.to_string(), Counters {
+                reads: n(3),
+                read_sectors: n(5),
+                read_ms: n(6),
+                writes: n(7),
+                write_sectors: n(9),
+                write_ms: n(10),
+                io_ms: n(12),
+            }))
+        })
+        .collect()
+}
+
+/// Whether `device` is a partition of another listed device: `sda1` of `sda`,
+/// or `nvme0n1p1` / `mmcblk0p1` of disks whose names end in a digit.
+fn is_partition(device: &str, devices: &[&str]) -> bool {
+    devices.iter().any(|disk| {
+        let Some(number) = device.strip_prefix(disk).filter(|rest| !rest.is_empty()) else {
+            return false;
+        };
+        let number = match disk.ends_with(|c: char| c.is_ascii_digit()) {
+            true => number.strip_prefix('p').unwrap_or_default(),
+            false => number,
+        };
+        !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+fn rates(device: String, now: Counters, before: Option<(Instant, Counters)>, at: Instant) -> DiskIo {
+    let mut io = DiskIo {
+        device,
+        read_bytes_per_sec: 0.0,
+        write_bytes_per_sec: 0.0,
+        read_iops: 0.0,
+        write_iops: 0.0,
+        await_ms: 0.0,
+        utilization_percentage: 0.0,
+        read_bytes_total: now.read_sectors * SECTOR_BYTES,
+        write_bytes_total: now.write_sectors * SECTOR_BYTES,
+        reads_total: now.reads,
+        writes_total: now.writes,
+    };
+
+    let Some((then, prev)) = before else {
+        return io;
+    };
+    let secs = at.duration_since(then).as_secs_f64();
+    if secs <= 0.0 {
+        return io;
+    }
+
+    // Counters wrap or reset when a device is re-added; treat that as idle.
+    let delta = |new: u64, old: u64| new.saturating_sub(old) as f64;
+    let reads = delta(now.reads, prev.reads);
+    let writes = delta(now.writes, prev.writes);
+
+    io.read_bytes_per_sec = delta(now.read_sectors, prev.read_sectors) * SECTOR_BYTES as f64 / secs;
+    io.write_bytes_per_sec = delta(now.write_sectors, prev.write_sectors) * SECTOR_BYTES as f64 / secs;
+    io.read_iops = reads / secs;
+    io.write_iops = writes / secs;
+    if reads + writes > 0.0 {
+        io.await_ms = (delta(now.read_ms, prev.read_ms) + delta(now.write_ms, prev.write_ms)) / (reads + writes);
+    }
+    io.utilization_percentage = (delta(now.io_ms, prev.io_ms) / (secs * 1000.0) * 100.0).min(100.0);
+
+    io
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISKSTATS: &str = "\
+   7       0 loop0 50 0 700 10 0 0 0 0 0 20 10 0 0 0 0 0 0
+   8       0 sda 1000 10 80000 500 2000 20 160000 1500 0 1800 2000 0 0 0 0 0 0
+   8       1 sda1 990 10 79000 490 1990 20 159000 1490 0 1790 1980 0 0 0 0 0 0
+   8      16 sdb 10 0 80 5 0 0 0 0 0 5 5
+ 259       0 nvme0n1 400 0 32000 100 800 0 64000 300 0 350 400 0 0 0 0 0 0
+ 259       1 nvme0n1p1 400 0 32000 100 800 0 64000 300 0 350 400 0 0 0 0 0 0
+ 179       0 mmcblk0 20 0 160 4 0 0 0 0 0 4 4
+ 179       1 mmcblk0p1 20 0 160 4 0 0 0 0 0 4 4
+   9       0 md0 300 0 24000 0 600 0 48000 0 0 0 0 0 0 0 0 0 0
+ 253       0 dm-0 300 0 24000 60 600 0 48000 180 0 200 240 0 0 0 0 0 0
+   8      32 sdc 1 2 3
+";
+
+    fn devices(counters: &HashMap<String, Counters>) -> Vec<&str> {
+        let mut names: Vec<&str> = counters.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn parses_whole_devices_only() {
+        let exclude = vec!["loop*".to_string()];
+        let counters = parse_diskstats(DISKSTATS, &exclude);
+
+        assert_eq!(devices(&counters), ["dm-0", "md0", "mmcblk0", "nvme0n1", "sda", "sdb"]);
+        let sda = counters["sda"];
+        assert_eq!((sda.reads, sda.read_sectors, sda.read_ms), (1000, 80000, 500));
+        assert_eq!((sda.writes, sda.write_sectors, sda.write_ms, sda.io_ms), (2000, 160000, 1500, 1800));
+    }
+
+    #[test]
+    fn keeps_partitions_of_unlisted_disks() {
+        let counters = parse_diskstats(" 8 1 sda1 1 0 8 1 1 0 8 1 0 2 2\n 8 17 sdab 1 0 8 1 1 0 8 1 0 2 2\n 8 0 sda 0 0 0 0 0 0 0 0 0 0 0", &[]);
+        assert_eq!(devices(&counters), ["sda", "sdab"]);
+        assert!(!is_partition("sda1", &["sdb"]));
+        assert!(!is_partition("nvme0n10", &["nvme0n1"]));
+    }
+
+    #[test]
+    fn computes_rates_between_samples() {
+        let counters = parse_diskstats(DISKSTATS, &[]);
+        let before = Counters { reads: 900, read_sectors: 70000, read_ms: 400, writes: 1900, write_sectors: 150000, write_ms: 1300, io_ms: 1300 };
+        let then = Instant::now();
+        let at = then + std::time::Duration::from_secs(2);
+
+        let io = rates("sda".to_string(), counters["sda"], Some((then, before)), at);
+        assert_eq!(io.read_bytes_per_sec, 10000.0 * 512.0 / 2.0);
+        assert_eq!(io.write_bytes_per_sec, 10000.0 * 512.0 / 2.0);
+        assert_eq!((io.read_iops, io.write_iops), (50.0, 50.0));
+        assert_eq!(io.await_ms, 300.0 / 200.0);
+        assert_eq!(io.utilization_percentage, 25.0);
+        assert_eq!(io.read_bytes_total, 80000 * 512);
+    }
+
+    #[test]
+    fn first_sample_and_counter_resets_are_idle() {
+        let counters = parse_diskstats(DISKSTATS, &[]);
+        let then = Instant::now();
+        let at = then + std::time::Duration::from_secs(1);
+
+        assert_eq!(rates("sda".to_string(), counters["sda"], None, at).read_iops, 0.0);
+
+        let reset = Counters { reads: u64::MAX, read_sectors: u64::MAX, ..counters["sda"] };
+        let io = rates("sda".to_string(), counters["sda"], Some((then, reset)), at);
+        assert_eq!((io.read_iops, io.read_bytes_per_sec, io.write_iops), (0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashSet;
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::mem::MaybeUninit;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// Filesystem types skipped unless `DISK_FS_TYPES_EXCLUDE` says otherwise.
+const DEFAULT_EXCLUDED_FS_TYPES: &str = "tmpfs,devtmpfs,overlay,squashfs,ramfs";
+
+#[derive(Serialize, Clone)]
+pub struct Filesystem {
+    pub device: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    pub used_percentage: f64,
+    pub total_inodes: u64,
+    pub used_inodes: u64,
+    pub inodes_used_percentage: Option<f64>,
+    pub read_only: bool,
+    pub bind_mount: bool,
+}
+
+struct Mount {
+    dev_id: String,
+    root: String,
+    mount_point: String,
+    options: String,
+    fs_type: String,
+    source: String,
+    super_options: String,
+}
+
+impl Mount {
+    /// btrfs mounts a subvolume such as `/@` as a subtree of the device, but
+    /// it is a filesystem in its own right, not a bind mount.
+    fn is_subvolume(&self) -> bool {
+        self.fs_type == "btrfs"
+            && self.super_options.split(',').any(|o| o.strip_prefix("subvol=") == Some(self.root.as_str()))
+    }
+}
+
+/// Lists mounted filesystems from `/proc/self/mountinfo` and measures each
+/// with `statvfs`.
+pub struct DiskCollector {
+    proc_root: PathBuf,
+    include_fs_types: Option<HashSet<String>>,
+    exclude_fs_types: HashSet<String>,
+    include_bind_mounts: bool,
+}
+
+impl DiskCollector {
+    pub fn from_env() -> Self {
+        let list = |key: &str| {
+            env::var(key).ok().map(|v| {
+                v.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect::<HashSet<_>>()
+            })
+        };
+
+        DiskCollector {
+            proc_root: PathBuf::from(env::var("PROC_ROOT").unwrap_or_else(|_| "/proc".to_string())),
+            include_fs_types: list("DISK_FS_TYPES_INCLUDE").filter(|types| !types.is_empty()),
+            exclude_fs_types: list("DISK_FS_TYPES_EXCLUDE").unwrap_or_else(|| {
+                DEFAULT_EXCLUDED_FS_TYPES.split(',').map(str::to_string).collect()
+            }),
+            include_bind_mounts: env::var("DISK_INCLUDE_BIND_MOUNTS").is_ok_and(|v| v == "true" || v == "1"),
+        }
+    }
+
+    // statvfs field widths differ between platforms, so the casts are only
+    // redundant on some of them.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn filesystems(&self) -> Vec<Filesystem> {
+        let mountinfo = fs::read_to_string(self.proc_root.join("self/mountinfo")).unwrap_or_default();
+        let mut filesystems = Vec::new();
+
+        for (mount, bind_mount) in classify_mounts(&mountinfo) {
+            if bind_mount && !self.include_bind_mounts {
+                continue;
+            }
+            if let Some(include) = &self.include_fs_types {
+                if !include.contains(&mount.fs_type) {
+                    continue;
+                }
+            } else if self.exclude_fs_types.contains(&mount.fs_type) {
+                continue;
+            }
+
+            let Some(stats) = statvfs(&mount.mount_point) else {
+                continue;
+            };
+            // proc, sysfs, cgroup and friends report no blocks at all.
+            if stats.f_blocks == 0 {
+                continue;
+            }
+
+            let block = stats.f_frsize as u64;
+            let total_bytes = stats.f_blocks as u64 * block;
+            let used_bytes = (stats.f_blocks as u64).saturating_sub(stats.f_bfree as u64) * block;
+            let available_bytes = stats.f_bavail as u64 * block;
+            let total_inodes = stats.f_files as u64;
+            let used_inodes = total_inodes.saturating_sub(stats.f_ffree as u64);
+
+            filesystems.push(Filesystem {
+                device: mount.source,
+                mount_point: mount.mount_point,
+                fs_type: mount.fs_type,
+                total_bytes,
+                used_bytes,
+                available_bytes,
+                used_percentage: percentage(used_bytes, used_bytes + available_bytes).unwrap_or(0.0),
+                total_inodes,
+                used_inodes,
+                inodes_used_percentage: percentage(used_inodes, total_inodes),
+                read_only: mount.options.split(',').any(|o| o == "ro")
+                    || stats.f_flag & libc::ST_RDONLY != 0,
+                bind_mount,
+            });
+        }
+
+        filesystems
+    }
+}
+
+fn percentage(part: u64, whole: u64) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64 * 100.0)
+}
+
+/// Parses mountinfo and flags bind mounts: mounts whose subtree of the device
+/// is already covered by an earlier mount, and subtree mounts other than
+/// btrfs subvolumes (e.g. a single file bound into a container).
+fn classify_mounts(mountinfo: &str) -> Vec<(Mount, bool)> {
+    let mut seen: Vec<(String, String)> = Vec::new();
+
+    mountinfo
+        .lines()
+        .filter_map(parse_mountinfo_line)
+        .map(|mount| {
+            let covered = seen.iter().any(|(dev_id, root)| {
+                *dev_id == mount.dev_id
+                    && (root == "/" || *root == mount.root || mount.root.starts_with(&format!("{}/", root)))
+            });
+            let bind_mount = covered || (mount.root != "/" && !mount.is_subvolume());
+            if !covered {
+                seen.push((mount.dev_id.clone(), mount.root.clone()));
+            }
+            (mount, bind_mount)
+        })
+        .collect()
+}
+
+/// Parses one line of `/proc/<pid>/mountinfo`:
+/// `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue`
+fn parse_mountinfo_line(line: &str) -> Option<Mount> {
+    let (left, right) = line.split_once(" - ")?;
+    let mut left = left.split(' ');
+    let mut right = right.split(' ');
+
+    let _mount_id = left.next()?;
+    let _parent_id = left.next()?;
+    let dev_id = left.next()?.to_string();
+    let root = unescape(left.next()?);
+    let mount_point = unescape(left.next()?);
+    let options = left.next()?.to_string();
+
+    Some(Mount {
+        dev_id,
+        root,
+        mount_point,
+        options,
+        fs_type: right.next()?.to_string(),
+        source: unescape(right.next()?),
+        super_options: right.next().unwrap_or_default().to_string(),
+    })
+}
+
+/// Decodes the octal escapes (`\040` for space) the kernel uses in mountinfo.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let code = u8::from_str_radix(&field[i + 1..i + 4], 8).unwrap_or(b'?');
+            out.push(code);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn statvfs(path: &str) -> Option<libc::statvfs> {
+    let path = CString::new(path).ok()?;
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: `path` is a valid NUL-terminated string and `stats` points to
+    // writable memory of the right size; it is only read after success.
+    let result = unsafe { libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) };
+    (result == 0).then(|| unsafe { stats.assume_init() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bind_mounts(mountinfo: &str) -> Vec<(String, bool)> {
+        classify_mounts(mountinfo).into_iter().map(|(m, bind)| (m.mount_point, bind)).collect()
+    }
+
+    #[test]
+    fn btrfs_subvolumes_are_not_bind_mounts() {
+        let mountinfo = "\
+59 1 0:30 /@ / rw,relatime shared:1 - btrfs /dev/nvme0n1p3 rw,compress=zstd:1,subvolid=256,subvol=/@
+60 59 0:30 /@home /home rw,relatime shared:2 - btrfs /dev/nvme0n1p3 rw,compress=zstd:1,subvolid=257,subvol=/@home
+61 59 259:1 / /boot rw,relatime shared:3 - ext4 /dev/nvme0n1p2 rw
+62 59 0:30 /@home/alice/share /srv/share rw,relatime shared:2 - btrfs /dev/nvme0n1p3 rw,subvolid=257,subvol=/@home
+63 59 259:1 / /mnt/boot-again rw,relatime shared:3 - ext4 /dev/nvme0n1p2 rw";
+
+        assert_eq!(
+            bind_mounts(mountinfo),
+            vec![
+                ("/".to_string(), false),
+                ("/home".to_string(), false),
+                ("/boot".to_string(), false),
+                ("/srv/share".to_string(), true),
+                ("/mnt/boot-again".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn subtree_mounts_of_other_filesystems_are_bind_mounts() {
+        let mountinfo = "\
+700 650 0:52 / / rw,relatime - overlay overlay rw,lowerdir=/l,upperdir=/u,workdir=/w
+701 700 254:1 /var/lib/docker/containers/abc/hosts /etc/hosts rw,relatime - ext4 /dev/vda1 rw
+702 700 254:1 /srv/data /data rw,relatime - ext4 /dev/vda1 rw";
+
+        assert_eq!(
+            bind_mounts(mountinfo),
+            vec![("/".to_string(), false), ("/etc/hosts".to_string(), true), ("/data".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn parses_escaped_fields() {
+        let mount = parse_mountinfo_line(
+            r"36 35 98:0 /my\040dir /mnt/with\040space\011tab rw,noatime master:1 - ext4 /dev/disk\134x rw",
+        )
+        .unwrap();
+
+        assert_eq!(mount.root, "/my dir");
+        assert_eq!(mount.mount_point, "/mnt/with space\ttab");
+        assert_eq!(mount.source, r"/dev/disk\x");
+        assert_eq!(mount.super_options, "rw");
+    }
+
+    #[test]
+    fn unescape_leaves_incomplete_escapes_alone() {
+        assert_eq!(unescape(r"a\040b"), "a b");
+        assert_eq!(unescape(r"tail\04"), r"tail\04");
+        assert_eq!(unescape(r"not\999octal"), r"not\999octal");
+        assert_eq!(unescape(r"end\040"), "end ");
+    }
+}
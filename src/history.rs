@@ -0,0 +1,187 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::auth::{Access, Section};
+use crate::sampler::format_timestamp;
+use crate::store::Store;
+
+/// Upper bound on buckets a single query may return, so a tiny `step` over a
+/// long `since` can't be used to make the server build a huge response.
+pub const MAX_BUCKETS: u64 = 10_000;
+
+/// Start of the trailing `since` window ending at `now`, or `None` when it
+/// would reach before the Unix epoch, which timestamps can't represent.
+pub fn window_start(now: SystemTime, since: Duration) -> Option<SystemTime> {
+    now.checked_sub(since).filter(|from| *from >= UNIX_EPOCH)
+}
+
+#[derive(Clone, Copy)]
+pub struct Point {
+    pub at: SystemTime,
+    pub value: f64,
+}
+
+#[derive(Serialize)]
+pub struct Bucket {
+    pub timestamp: String,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    pub metric: String,
+    pub since: String,
+    pub step_seconds: f64,
+    pub points: Vec<Bucket>,
+}
+
+/// Recent points of one metric and the status sections they come from.
+#[derive(Default)]
+struct Series {
+    points: VecDeque<Point>,
+    sections: &'static [Section],
+}
+
+/// Fixed-capacity ring buffer of recent points for every sampled metric,
+/// optionally backed by an on-disk [`Store`] that outlives the process.
+/// Only the ring buffers sit behind the lock; the store serializes its own
+/// access, so `/history` readers never wait on a writer's disk I/O.
+pub struct History {
+    capacity: usize,
+    series: RwLock<HashMap<String, Series>>,
+    store: Option<Arc<Store>>,
+}
+
+pub type SharedHistory = Arc<History>;
+
+impl History {
+    pub fn new(capacity: usize, store: Option<Arc<Store>>) -> Self {
+        History { capacity: capacity.max(1), series: RwLock::new(HashMap::new()), store }
+    }
+
+    /// Appends one sample's points, which expose the data of `sections`.
+    pub fn record(&self, at: SystemTime, points: &[(String, f64)], sections: &'static [Section]) {
+        {
+            let mut series = self.series.write().unwrap();
+            for (metric, value) in points {
+                let series = series.entry(metric.clone()).or_default();
+                series.sections = sections;
+                if series.points.len() == self.capacity {
+                    series.points.pop_front();
+                }
+                series.points.push_back(Point { at, value: *value });
+            }
+        }
+
+        if let Some(store) = &self.store
+            && !points.is_empty()
+            && let Err(e) = store.record(at, points)
+        {
+            eprintln!("Failed to persist metrics: {}", e);
+        }
+    }
+
+    /// Whether `access` may read `metric`: none of the sections it was
+    /// recorded under may be hidden. Metrics not sampled since startup, known
+    /// only to the store, are left to tokens that hide nothing.
+    pub fn visible_to(&self, metric: &str, access: &Access) -> bool {
+        match self.series.read().unwrap().get(metric) {
+            Some(series) => access.can_see_all(series.sections),
+            None => access.hide.is_empty(),
+        }
+    }
+
+    pub fn metrics(&self) -> Result<Vec<String>, String> {
+        let mut names: Vec<String> = self.series.read().unwrap().keys().cloned().collect();
+        if let Some(store) = &self.store {
+            names.extend(store.metrics().map_err(|e| e.to_string())?);
+        }
+        names.sort_unstable();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Downsampled series for `metric` from `from` until now, read from the
+    /// store when one is configured. `None` means the metric is unknown.
+    pub fn query(&self, metric: &str, from: SystemTime, step: Duration) -> Result<Option<Vec<Bucket>>, String> {
+        if let Some(store) = &self.store {
+            return store.query(metric, from, step).map_err(|e| e.to_string());
+        }
+
+        Ok(self.series.read().unwrap().get(metric).map(|series| {
+            let points: Vec<Point> = series.points.iter().filter(|p| p.at >= from).copied().collect();
+            downsample(&points, step)
+        }))
+    }
+}
+
+/// Groups points into `step`-wide buckets aligned to the Unix epoch and
+/// reduces each bucket to min/avg/max. Empty buckets are omitted.
+pub fn downsample(points: &[Point], step: Duration) -> Vec<Bucket> {
+    let step_secs = step.as_secs_f64();
+    let mut buckets: Vec<(f64, Vec<f64>)> = Vec::new();
+
+    for point in points {
+        let secs = point.at.duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or_default();
+        let start = (secs / step_secs).floor() * step_secs;
+
+        match buckets.last_mut() {
+            Some((last_start, values)) if *last_start == start => values.push(point.value),
+            _ => buckets.push((start, vec![point.value])),
+        }
+    }
+
+    buckets.into_iter()
+        .map(|(start, values)| Bucket {
+            timestamp: format_timestamp(UNIX_EPOCH + Duration::from_secs_f64(start)),
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            avg: values.iter().sum::<f64>() / values.len() as f64,
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            count: values.len(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_start_stops_at_the_epoch() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_800_000_000);
+
+        assert_eq!(window_start(now, Duration::from_secs(3600)), Some(now - Duration::from_secs(3600)));
+        assert_eq!(window_start(now, Duration::from_secs(1_800_000_000)), Some(UNIX_EPOCH));
+        assert_eq!(window_start(now, Duration::from_secs(1_800_000_001)), None);
+        assert_eq!(window_start(now, humantime::parse_duration("100years").unwrap()), None);
+        assert_eq!(window_start(now, humantime::parse_duration("500000000000years").unwrap()), None);
+    }
+
+    #[test]
+    fn metrics_are_visible_by_the_sections_they_were_recorded_under() {
+        let history = History::new(10, None);
+        history.record(UNIX_EPOCH, &[("disk_temp".to_string(), 40.0)], Section::TEMPERATURES);
+        history.record(UNIX_EPOCH, &[("cpu_percentage".to_string(), 3.0)], &[Section::Usage]);
+        history.record(UNIX_EPOCH, &[("disk_used_percentage:/".to_string(), 50.0)], &[Section::Disks]);
+
+        let hiding = |hide: Vec<Section>| Access { name: "test".to_string(), scopes: Vec::new(), expires_at: None, hide };
+        let no_temps = hiding(vec![Section::Temps]);
+        assert!(!history.visible_to("disk_temp", &no_temps));
+        assert!(history.visible_to("disk_used_percentage:/", &no_temps));
+        assert!(history.visible_to("cpu_percentage", &no_temps));
+
+        let no_usage = hiding(vec![Section::Usage]);
+        assert!(!history.visible_to("disk_temp", &no_usage));
+        assert!(!history.visible_to("cpu_percentage", &no_usage));
+
+        // Only in the store, e.g. from before a restart: unknown sections.
+        assert!(!history.visible_to("probe_avg_ms:icmp:8.8.8.8", &hiding(vec![Section::Disks])));
+        assert!(history.visible_to("probe_avg_ms:icmp:8.8.8.8", &hiding(Vec::new())));
+    }
+}
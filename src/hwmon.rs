@@ -0,0 +1,395 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorKind {
+    Temperature,
+    Fan,
+    Voltage,
+    Current,
+    Power,
+}
+
+impl SensorKind {
+    const ALL: [SensorKind; 5] = [
+        SensorKind::Temperature,
+        SensorKind::Fan,
+        SensorKind::Voltage,
+        SensorKind::Current,
+        SensorKind::Power,
+    ];
+
+    /// hwmon file prefix, e.g. `in` for `in0_input`.
+    fn prefix(self) -> &'static str {
+        match self {
+            SensorKind::Temperature => "temp",
+            SensorKind::Fan => "fan",
+            SensorKind::Voltage => "in",
+            SensorKind::Current => "curr",
+            SensorKind::Power => "power",
+        }
+    }
+
+    /// Divisor from the raw sysfs integer to `unit`.
+    fn scale(self) -> f64 {
+        match self {
+            SensorKind::Fan => 1.0,
+            SensorKind::Power => 1_000_000.0,
+            _ => 1000.0,
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            SensorKind::Temperature => "celsius",
+            SensorKind::Fan => "rpm",
+            SensorKind::Voltage => "volts",
+            SensorKind::Current => "amperes",
+            SensorKind::Power => "watts",
+        }
+    }
+}
+
+/// One hwmon channel (or thermal zone) with its limits, already converted to
+/// `unit`.
+#[derive(Serialize, Clone)]
+pub struct Sensor {
+    pub kind: SensorKind,
+    /// Channel within the device, e.g. `temp1` or `fan2`; thermal zones have
+    /// a single `temp` channel.
+    pub channel: String,
+    pub label: Option<String>,
+    pub value: f64,
+    pub unit: &'static str,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub critical: Option<f64>,
+    pub alarm: Option<bool>,
+}
+
+impl Sensor {
+    pub fn display_name(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.channel)
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Chip {
+    /// sysfs device directory, e.g. `hwmon3` or `thermal_zone0`; unique even
+    /// when several chips share a name.
+    pub device: String,
+    pub name: String,
+    pub sensors: Vec<Sensor>,
+}
+
+/// A single temperature channel, flattened out of its chip for `TempData`.
+#[derive(Serialize, Clone)]
+pub struct TempReading {
+    pub device: String,
+    pub chip: String,
+    pub channel: String,
+    pub label: Option<String>,
+    pub celsius: f32,
+    pub max: Option<f32>,
+    pub critical: Option<f32>,
+}
+
+/// Flattens the temperature sensors of every chip.
+pub fn temperatures(chips: &[Chip]) -> Vec<TempReading> {
+    chips.iter()
+        .flat_map(|chip| chip.sensors.iter()
+            .filter(|s| s.kind == SensorKind::Temperature)
+            .map(move |s| TempReading {
+                device: chip.device.clone(),
+                chip: chip.name.clone(),
+                channel: s.channel.clone(),
+                label: s.label.clone(),
+                celsius: s.value as f32,
+                max: s.max.map(|v| v as f32),
+                critical: s.critical.map(|v| v as f32),
+            }))
+        .collect()
+}
+
+/// Reads hardware sensors straight from `/sys/class/hwmon` and
+/// `/sys/class/thermal`. The root is configurable so fixture trees can stand
+/// in for a real `/sys`.
+#[derive(Clone)]
+pub struct Sysfs {
+    root: PathBuf,
+}
+
+impl Sysfs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Sysfs { root: root.into() }
+    }
+
+    pub fn from_env() -> Self {
+        Sysfs::new(env::var("SYSFS_ROOT").unwrap_or_else(|_| "/sys".to_string()))
+    }
+
+    /// Every hwmon chip followed by every thermal zone.
+    pub fn chips(&self) -> Vec<Chip> {
+        let mut chips = self.hwmon_chips();
+        chips.extend(self.thermal_zones());
+        chips
+    }
+
+    fn hwmon_chips(&self) -> Vec<Chip> {
+        sorted_entries(&self.root.join("class/hwmon"), "hwmon")
+            .into_iter()
+            .map(|dir| {
+                let mut sensors = Vec::new();
+                for kind in SensorKind::ALL {
+                    let mut names = channels(&dir, kind.prefix(), "_input");
+                    if kind == SensorKind::Power {
+                        names.extend(channels(&dir, kind.prefix(), "_average"));
+                        names.sort_by_key(|c| natural_key(c));
+                        names.dedup();
+                    }
+
+                    for channel in names {
+                        if let Some(sensor) = read_hwmon_sensor(&dir, kind, channel) {
+                            sensors.push(sensor);
+                        }
+                    }
+                }
+
+                Chip {
+                    device: file_name(&dir),
+                    name: read_trimmed(&dir.join("name")).unwrap_or_else(|| file_name(&dir)),
+                    sensors,
+                }
+            })
+            .collect()
+    }
+
+    fn thermal_zones(&self) -> Vec<Chip> {
+        let mut chips = Vec::new();
+
+        for dir in sorted_entries(&self.root.join("class/thermal"), "thermal_zone") {
+            let Some(value) = read_scaled(&dir.join("temp"), 1000.0) else {
+                continue;
+            };
+            let zone_type = read_trimmed(&dir.join("type")).unwrap_or_else(|| file_name(&dir));
+
+            let mut max = None;
+            let mut critical = None;
+            for trip in channels(&dir, "trip_point_", "_type") {
+                let temp = read_scaled(&dir.join(format!("{}_temp", trip)), 1000.0);
+                match read_trimmed(&dir.join(format!("{}_type", trip))).as_deref() {
+                    Some("critical") => critical = temp,
+                    Some("hot") => max = temp,
+                    _ => {}
+                }
+            }
+
+            chips.push(Chip {
+                device: file_name(&dir),
+                name: zone_type.clone(),
+                sensors: vec![Sensor {
+                    kind: SensorKind::Temperature,
+                    channel: "temp".to_string(),
+                    label: Some(zone_type),
+                    value,
+                    unit: SensorKind::Temperature.unit(),
+                    min: None,
+                    max,
+                    critical,
+                    alarm: None,
+                }],
+            });
+        }
+
+        chips
+    }
+}
+
+fn read_hwmon_sensor(dir: &Path, kind: SensorKind, channel: String) -> Option<Sensor> {
+    let scale = kind.scale();
+    let attr = |name: &str| dir.join(format!("{}_{}", channel, name));
+
+    let value = read_scaled(&attr("input"), scale)
+        .or_else(|| (kind == SensorKind::Power).then(|| read_scaled(&attr("average"), scale)).flatten())?;
+
+    Some(Sensor {
+        kind,
+        label: read_trimmed(&attr("label")),
+        value,
+        unit: kind.unit(),
+        min: read_scaled(&attr("min"), scale),
+        max: read_scaled(&attr("max"), scale).or_else(|| read_scaled(&attr("cap"), scale)),
+        critical: read_scaled(&attr("crit"), scale),
+        alarm: read_trimmed(&attr("alarm")).map(|a| a != "0"),
+        channel,
+    })
+}
+
+/// Entries of `dir` whose names start with `prefix`, in natural order so
+/// `hwmon10` sorts after `hwmon2`.
+fn sorted_entries(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|rd| rd.filter_map(Result::ok).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    entries.retain(|p| file_name(p).starts_with(prefix));
+    entries.sort_by_key(|p| natural_key(&file_name(p)));
+    entries
+}
+
+/// Channel names like `temp1` found via files named `<prefix><n><suffix>`.
+fn channels(dir: &Path, prefix: &str, suffix: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|rd| rd.filter_map(Result::ok).map(|e| e.file_name().to_string_lossy().to_string()).collect())
+        .unwrap_or_default();
+    names.retain(|n| {
+        n.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(suffix))
+            .is_some_and(|index| !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()))
+    });
+
+    let mut channels: Vec<String> = names.into_iter()
+        .map(|n| n.trim_end_matches(suffix).to_string())
+        .collect();
+    channels.sort_by_key(|c| natural_key(c));
+    channels
+}
+
+fn natural_key(name: &str) -> (String, u64) {
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (stem, index) = name.split_at(name.len() - digits);
+    (stem.to_string(), index.parse().unwrap_or(0))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Reads a raw sysfs integer (millidegrees, millivolts, microwatts…) and
+/// divides it down to the base unit.
+fn read_scaled(path: &Path, scale: f64) -> Option<f64> {
+    read_trimmed(path)?.parse::<f64>().ok().map(|v| v / scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    /// A throwaway `/sys` tree, removed again when dropped.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let root = env::temp_dir().join(format!("status_server-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&root);
+            Fixture(root)
+        }
+
+        fn write(&self, path: &str, contents: &str) -> &Self {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+            self
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_hwmon_channels_with_labels_limits_and_scaling() {
+        let sys = Fixture::new("hwmon");
+        sys.write("class/hwmon/hwmon2/name", "coretemp\n")
+            .write("class/hwmon/hwmon2/temp2_input", "45000\n")
+            .write("class/hwmon/hwmon2/temp1_input", "52500\n")
+            .write("class/hwmon/hwmon2/temp1_label", "Package id 0\n")
+            .write("class/hwmon/hwmon2/temp1_max", "80000\n")
+            .write("class/hwmon/hwmon2/temp1_crit", "100000\n")
+            .write("class/hwmon/hwmon10/name", "nct6798\n")
+            .write("class/hwmon/hwmon10/fan1_input", "1200\n")
+            .write("class/hwmon/hwmon10/fan1_min", "300\n")
+            .write("class/hwmon/hwmon10/fan1_alarm", "1\n")
+            .write("class/hwmon/hwmon10/in0_input", "1128\n")
+            .write("class/hwmon/hwmon10/curr1_input", "2500\n")
+            .write("class/hwmon/hwmon10/power1_average", "15250000\n")
+            .write("class/hwmon/hwmon10/power1_cap", "65000000\n")
+            .write("class/hwmon/hwmon1/temp1_input", "38000\n");
+
+        let chips = Sysfs::new(&sys.0).chips();
+
+        let devices: Vec<&str> = chips.iter().map(|c| c.device.as_str()).collect();
+        assert_eq!(devices, ["hwmon1", "hwmon2", "hwmon10"]);
+        assert_eq!(chips[0].name, "hwmon1", "falls back to the directory name");
+
+        let coretemp = &chips[1];
+        assert_eq!(coretemp.name, "coretemp");
+        let channels: Vec<&str> = coretemp.sensors.iter().map(|s| s.channel.as_str()).collect();
+        assert_eq!(channels, ["temp1", "temp2"]);
+        let package = &coretemp.sensors[0];
+        assert_eq!(package.display_name(), "Package id 0");
+        assert_eq!((package.value, package.max, package.critical), (52.5, Some(80.0), Some(100.0)));
+        assert_eq!((package.unit, package.alarm), ("celsius", None));
+        assert_eq!(coretemp.sensors[1].display_name(), "temp2");
+
+        let nct = &chips[2];
+        let readings: Vec<(&str, f64)> = nct.sensors.iter().map(|s| (s.unit, s.value)).collect();
+        assert_eq!(readings, [("rpm", 1200.0), ("volts", 1.128), ("amperes", 2.5), ("watts", 15.25)]);
+        assert_eq!((nct.sensors[0].min, nct.sensors[0].alarm), (Some(300.0), Some(true)));
+        assert_eq!(nct.sensors[3].max, Some(65.0), "power cap is reported as max");
+    }
+
+    #[test]
+    fn reads_thermal_zones_with_trip_points() {
+        let sys = Fixture::new("thermal");
+        sys.write("class/thermal/thermal_zone0/type", "x86_pkg_temp\n")
+            .write("class/thermal/thermal_zone0/temp", "61000\n")
+            .write("class/thermal/thermal_zone0/trip_point_0_type", "passive\n")
+            .write("class/thermal/thermal_zone0/trip_point_0_temp", "90000\n")
+            .write("class/thermal/thermal_zone0/trip_point_1_type", "hot\n")
+            .write("class/thermal/thermal_zone0/trip_point_1_temp", "95000\n")
+            .write("class/thermal/thermal_zone0/trip_point_2_type", "critical\n")
+            .write("class/thermal/thermal_zone0/trip_point_2_temp", "105000\n")
+            .write("class/thermal/thermal_zone11/type", "acpitz\n")
+            .write("class/thermal/thermal_zone11/temp", "27800\n")
+            .write("class/thermal/thermal_zone3/type", "iwlwifi_1\n")
+            .write("class/thermal/cooling_device0/type", "Processor\n");
+
+        let chips = Sysfs::new(&sys.0).chips();
+
+        let devices: Vec<&str> = chips.iter().map(|c| c.device.as_str()).collect();
+        assert_eq!(devices, ["thermal_zone0", "thermal_zone11"], "zones without a reading are skipped");
+
+        let package = &chips[0].sensors[0];
+        assert_eq!(package.display_name(), "x86_pkg_temp");
+        assert_eq!((package.value, package.max, package.critical), (61.0, Some(95.0), Some(105.0)));
+        assert_eq!(chips[1].sensors[0].value, 27.8);
+
+        let temps = temperatures(&chips);
+        assert_eq!((temps[1].chip.as_str(), temps[1].celsius), ("acpitz", 27.8));
+    }
+
+    #[test]
+    fn missing_sysfs_yields_no_chips() {
+        assert!(Sysfs::new(Fixture::new("empty").0.clone()).chips().is_empty());
+    }
+
+    #[test]
+    fn natural_key_orders_by_trailing_number() {
+        let mut names = vec!["temp10", "temp2", "temp1", "fan3"];
+        names.sort_by_key(|n| natural_key(n));
+        assert_eq!(names, ["fan3", "temp1", "temp2", "temp10"]);
+    }
+}
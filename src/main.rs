@@ -1,243 +1,715 @@
-use actix_web::{get, App, HttpResponse, HttpServer, HttpRequest, Responder, web};
-use serde::Serialize;
-use std::process::Command;
-use get_if_addrs::get_if_addrs;
-use sysinfo::System;
+mod alerts;
+mod auth;
+mod collectors;
+mod config;
+mod ddns;
+mod diskio;
+mod disks;
+mod history;
+mod hwmon;
+mod metrics;
+mod models;
+mod netif;
+mod notify;
+mod probes;
+mod publicip;
+mod roles;
+mod sampler;
+mod speedtest;
+mod store;
+mod throughput;
+mod tls;
+
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::dev::{Extensions, Service};
+use actix_web::rt::net::TcpStream;
+use actix_web::{get, post, App, HttpMessage, HttpResponse, HttpServer, HttpRequest, Responder, web};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::any::Any;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 use std::env;
+use std::process;
 use dotenv::dotenv;
 
-#[derive(Serialize)]
-struct TempData {
-    motherboard_temp: Option<f32>,
-    cpu_temp: Option<f32>,
-    gpu_temp: Option<f32>,
-}
-
-#[derive(Serialize)]
-struct ServerData {
-    server_name: Option<String>,
-    server_cpu: String,
-    server_os: Option<String>,
-}
-
-#[derive(Serialize)]
-struct UsageData {
-    cpu_percentage: f32,
-    memory: f32,
-    total_memory: f32,
-    memory_percentage: f32,
-    temps: TempData,
-}
-
-#[derive(Serialize)]
-struct NetworkInterface {
-    name: String,
-    ip: String,
-}
-
-#[derive(Serialize)]
-struct NetworkData {
-    public_ip: String,
-    ping_ms: Option<f64>,
-    speed_download_mbps: Option<f64>,
-    speed_upload_mbps: Option<f64>,
-    interfaces: Vec<NetworkInterface>,
-}
-
-#[derive(Serialize)]
-struct StatusResponse {
-    server_status: String,
-    server_uptime: String,
-    server_data: ServerData,
-    data: UsageData,
-    network: NetworkData,
-}
-
-fn get_ping_ms() -> Option<f64> {
-    let output = Command::new("ping")
-        .arg("-c")
-        .arg("1")
-        .arg("8.8.8.8")
-        .output()
-        .ok()?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    stdout.lines()
-        .find(|line| line.contains("time="))
-        .and_then(|line| {
-            line.split("time=").nth(1)?.split(' ').next()?.parse::<f64>().ok()
-        })
+use crate::alerts::{AlertEngine, Rule, SharedAlerts};
+use crate::auth::{ApiToken, AuthError, Authenticated, CertMapping, ClientCert, Scope, Section, TokenHash, Tokens};
+use crate::ddns::{Ddns, SharedDdnsStatus, Updater};
+use crate::history::{History, HistoryResponse, SharedHistory, MAX_BUCKETS};
+use crate::models::{SensorsResponse, ServerData};
+use crate::notify::{Destination, Notifier, SharedDeliveryLog};
+use crate::publicip::{IpMonitor, SharedIpEvents};
+use crate::store::{Retention, Store};
+use crate::sampler::{Intervals, Sampled, SharedSnapshot};
+use crate::speedtest::{Backend, Speedtests, Trigger};
+use crate::throughput::UploadReport;
+use crate::tls::TlsSettings;
+
+#[get("/status")]
+async fn status(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    snapshot: web::Data<SharedSnapshot>,
+    server_data: web::Data<ServerData>,
+) -> impl Responder {
+    let token = match tokens.authorize(&req, Scope::ReadStatus) {
+        Ok(token) => token,
+        Err(e) => return e.response(),
+    };
+
+    let response = snapshot.read().unwrap().status(&server_data);
+    let mut response = serde_json::to_value(response).unwrap_or_default();
+    token.redact(&mut response);
+    HttpResponse::Ok().json(response)
 }
 
-fn get_speedtest() -> Option<(f64, f64)> {
-    let output = Command::new("speedtest-cli")
-        .arg("--simple")
-        .output()
-        .ok()?;
+#[get("/metrics")]
+async fn prometheus_metrics(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    snapshot: web::Data<SharedSnapshot>,
+    server_data: web::Data<ServerData>,
+) -> impl Responder {
+    if let Err(e) = tokens.authorize_metrics(&req) {
+        return e.response();
+    }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut download = None;
-    let mut upload = None;
+    let body = metrics::render(&snapshot.read().unwrap(), &server_data);
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(body)
+}
 
-    for line in stdout.lines() {
-        if line.starts_with("Download:") {
-            download = line.split_whitespace().nth(1)?.parse::<f64>().ok();
-        } else if line.starts_with("Upload:") {
-            upload = line.split_whitespace().nth(1)?.parse::<f64>().ok();
-        }
+#[derive(Deserialize)]
+struct HistoryQuery {
+    metric: Option<String>,
+    since: Option<String>,
+    step: Option<String>,
+}
+
+#[get("/sensors")]
+async fn sensor_inventory(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    snapshot: web::Data<SharedSnapshot>,
+) -> impl Responder {
+    match tokens.authorize(&req, Scope::ReadStatus) {
+        Ok(token) if !token.can_see_all(Section::TEMPERATURES) => return AuthError::Forbidden.response(),
+        Ok(_) => {}
+        Err(e) => return e.response(),
     }
 
-    match (download, upload) {
-        (Some(d), Some(u)) => Some((d, u)),
-        _ => None,
+    let snapshot = snapshot.read().unwrap();
+    HttpResponse::Ok().json(SensorsResponse {
+        chips: snapshot.sensors.as_ref().map(|s| s.value.chips.clone()).unwrap_or_default(),
+        sampled_at: snapshot.sensors.as_ref().map(Sampled::timestamp),
+    })
+}
+
+#[get("/history")]
+async fn history_query(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    history: web::Data<SharedHistory>,
+    query: web::Query<HistoryQuery>,
+) -> impl Responder {
+    let token = match tokens.authorize(&req, Scope::ReadHistory) {
+        Ok(token) => token,
+        Err(e) => return e.response(),
+    };
+
+    let Some(metric) = &query.metric else {
+        return match history.metrics() {
+            Ok(mut metrics) => {
+                metrics.retain(|metric| history.visible_to(metric, token));
+                HttpResponse::Ok().json(serde_json::json!({ "metrics": metrics }))
+            }
+            Err(e) => HttpResponse::InternalServerError().body(format!("History unavailable: {}", e)),
+        };
+    };
+
+    if !history.visible_to(metric, token) {
+        return AuthError::Forbidden.response();
     }
+
+    let since = match humantime::parse_duration(query.since.as_deref().unwrap_or("1h")) {
+        Ok(since) => since,
+        Err(e) => return HttpResponse::BadRequest().body(format!("Invalid since: {}", e)),
+    };
+    let Some(from) = history::window_start(SystemTime::now(), since) else {
+        return HttpResponse::BadRequest().body("Invalid since: reaches back before 1970");
+    };
+    let step = match humantime::parse_duration(query.step.as_deref().unwrap_or("1m")) {
+        Ok(step) if !step.is_zero() => step,
+        Ok(_) => return HttpResponse::BadRequest().body("Invalid step: must be greater than zero"),
+        Err(e) => return HttpResponse::BadRequest().body(format!("Invalid step: {}", e)),
+    };
+    if since.as_secs_f64() / step.as_secs_f64() > MAX_BUCKETS as f64 {
+        return HttpResponse::BadRequest().body(format!("Too many buckets: since/step must not exceed {}", MAX_BUCKETS));
+    }
+
+    let points = match history.query(metric, from, step) {
+        Ok(Some(points)) => points,
+        Ok(None) => return HttpResponse::NotFound().body(format!("Unknown metric: {}", metric)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("History unavailable: {}", e)),
+    };
+
+    HttpResponse::Ok().json(HistoryResponse {
+        metric: metric.clone(),
+        since: sampler::format_timestamp(from),
+        step_seconds: step.as_secs_f64(),
+        points,
+    })
 }
 
-fn get_network_interfaces() -> Vec<NetworkInterface> {
-    get_if_addrs().unwrap_or_default()
-        .into_iter()
-        .map(|iface| NetworkInterface {
-            name: iface.name.clone(),
-            ip: iface.ip().to_string(),
-        })
-        .collect()
+#[get("/alerts")]
+async fn active_alerts(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    engine: web::Data<SharedAlerts>,
+    history: web::Data<SharedHistory>,
+) -> impl Responder {
+    let token = match tokens.authorize(&req, Scope::ReadStatus) {
+        Ok(token) => token,
+        Err(e) => return e.response(),
+    };
+
+    let mut alerts = engine.read().unwrap().response();
+    alerts.active.retain(|alert| history.visible_to(&alert.metric, token));
+    alerts.resolved.retain(|alert| history.visible_to(&alert.metric, token));
+    HttpResponse::Ok().json(alerts)
 }
 
-fn get_all_temps() -> TempData {
-    let output = Command::new("sensors").output().ok();
-    let stdout = output.map(|o| String::from_utf8_lossy(&o.stdout).to_string()).unwrap_or_default();
+#[get("/notifications")]
+async fn notification_log(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    log: web::Data<SharedDeliveryLog>,
+) -> impl Responder {
+    if let Err(e) = tokens.authorize(&req, Scope::Admin) {
+        return e.response();
+    }
 
-    let mut current_chip: Option<String> = None;
-    let mut motherboard_temp = None;
-    let mut cpu_temp = None;
-    let mut gpu_temp = None;
+    let deliveries: Vec<_> = log.read().unwrap().iter().cloned().collect();
+    HttpResponse::Ok().json(serde_json::json!({ "deliveries": deliveries }))
+}
 
-    for line in stdout.lines() {
-        if !line.starts_with(' ') && !line.is_empty() && !line.contains(':') {
-            current_chip = Some(line.to_string());
-        }
+#[get("/speedtest")]
+async fn speedtest_jobs(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    speedtests: web::Data<Speedtests>,
+) -> impl Responder {
+    match tokens.authorize(&req, Scope::ReadStatus) {
+        Ok(token) if !token.can_see(Section::Speedtest) => return AuthError::Forbidden.response(),
+        Ok(_) => {}
+        Err(e) => return e.response(),
+    }
 
-        if let Some(chip) = &current_chip {
-            let lower = chip.to_lowercase();
+    HttpResponse::Ok().json(speedtests.response())
+}
 
-            if (lower.contains("asus") || lower.contains("acpitz")) && line.trim().to_lowercase().contains("temp1:") {
-                motherboard_temp = parse_temp_line(line);
-            } else if lower.contains("k10temp") && line.trim().to_lowercase().contains("temp1:") {
-                cpu_temp = parse_temp_line(line);
-            } else if lower.contains("amdgpu") && line.trim().to_lowercase().contains("edge:") {
-                gpu_temp = parse_temp_line(line);
-            }
-        }
+#[get("/speedtest/{id}")]
+async fn speedtest_job(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    speedtests: web::Data<Speedtests>,
+    id: web::Path<u64>,
+) -> impl Responder {
+    match tokens.authorize(&req, Scope::ReadStatus) {
+        Ok(token) if !token.can_see(Section::Speedtest) => return AuthError::Forbidden.response(),
+        Ok(_) => {}
+        Err(e) => return e.response(),
     }
 
-    TempData {
-        motherboard_temp,
-        cpu_temp,
-        gpu_temp,
+    match speedtests.job(id.into_inner()) {
+        Some(job) => HttpResponse::Ok().json(job),
+        None => HttpResponse::NotFound().body("Unknown speed test job"),
     }
 }
 
-fn parse_temp_line(line: &str) -> Option<f32> {
-    for word in line.split_whitespace() {
-        if word.contains("°C") {
-            let clean = word.trim_matches(|c| c == '+' || c == '°' || c == 'C');
-            return clean.parse::<f32>().ok();
+#[post("/speedtest")]
+async fn trigger_speedtest(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    speedtests: web::Data<Speedtests>,
+) -> impl Responder {
+    let token = match tokens.authorize(&req, Scope::TriggerJobs) {
+        Ok(token) => token,
+        Err(e) => return e.response(),
+    };
+
+    HttpResponse::Accepted().json(speedtests.trigger(Trigger::Manual, Some(&token.name)))
+}
+
+#[get("/public-ip/events")]
+async fn public_ip_events(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    snapshot: web::Data<SharedSnapshot>,
+    events: web::Data<SharedIpEvents>,
+) -> impl Responder {
+    match tokens.authorize(&req, Scope::ReadStatus) {
+        Ok(token) if !token.can_see(Section::PublicIp) => return AuthError::Forbidden.response(),
+        Ok(_) => {}
+        Err(e) => return e.response(),
+    }
+
+    let current = snapshot.read().unwrap().public_ip.as_ref().map(|s| s.value.clone()).unwrap_or_default();
+    let events: Vec<_> = events.read().unwrap().iter().cloned().collect();
+    HttpResponse::Ok().json(serde_json::json!({
+        "ipv4": current.ipv4,
+        "ipv6": current.ipv6,
+        "events": events,
+    }))
+}
+
+#[get("/ddns")]
+async fn ddns_report(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    records: web::Data<SharedDdnsStatus>,
+) -> impl Responder {
+    match tokens.authorize(&req, Scope::ReadStatus) {
+        Ok(token) if !token.can_see(Section::PublicIp) => return AuthError::Forbidden.response(),
+        Ok(_) => {}
+        Err(e) => return e.response(),
+    }
+
+    let records: Vec<_> = records.read().unwrap().clone();
+    HttpResponse::Ok().json(serde_json::json!({ "records": records }))
+}
+
+/// Whether this instance serves the `/throughput` endpoints used by other
+/// instances' native speed tests.
+struct ThroughputServer(bool);
+
+#[derive(Deserialize)]
+struct DownloadQuery {
+    duration_ms: Option<u64>,
+}
+
+#[get("/throughput/ping")]
+async fn throughput_ping(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    server: web::Data<ThroughputServer>,
+) -> impl Responder {
+    if !server.0 {
+        return HttpResponse::NotFound().finish();
+    }
+    if let Err(e) = tokens.authorize(&req, Scope::TriggerJobs) {
+        return e.response();
+    }
+
+    HttpResponse::NoContent().finish()
+}
+
+#[get("/throughput/download")]
+async fn throughput_download(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    server: web::Data<ThroughputServer>,
+    query: web::Query<DownloadQuery>,
+) -> impl Responder {
+    if !server.0 {
+        return HttpResponse::NotFound().finish();
+    }
+    if let Err(e) = tokens.authorize(&req, Scope::TriggerJobs) {
+        return e.response();
+    }
+
+    let duration = Duration::from_millis(query.duration_ms.unwrap_or(10_000));
+    HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .streaming(throughput::download_stream(duration))
+}
+
+#[post("/throughput/upload")]
+async fn throughput_upload(
+    req: HttpRequest,
+    tokens: web::Data<Tokens>,
+    server: web::Data<ThroughputServer>,
+    mut payload: web::Payload,
+) -> impl Responder {
+    if !server.0 {
+        return HttpResponse::NotFound().finish();
+    }
+    if let Err(e) = tokens.authorize(&req, Scope::TriggerJobs) {
+        return e.response();
+    }
+
+    let started = Instant::now();
+    let deadline = started + throughput::MAX_DURATION;
+    let mut bytes = 0u64;
+    // Stops reading at MAX_DURATION even if the client keeps sending.
+    while let Ok(Some(chunk)) =
+        actix_web::rt::time::timeout(deadline.saturating_duration_since(Instant::now()), payload.next()).await
+    {
+        match chunk {
+            Ok(chunk) => bytes += chunk.len() as u64,
+            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
         }
     }
-    None
+
+    HttpResponse::Ok().json(UploadReport { bytes, seconds: started.elapsed().as_secs_f64() })
 }
 
-#[get("/status")]
-async fn status(req: HttpRequest, token: web::Data<String>) -> impl Responder {
-    if let Some(auth_header) = req.headers().get("Authorization") {
-        if let Ok(auth_str) = auth_header.to_str() {
-            if auth_str == format!("Bearer {}", token.get_ref()) {
-
-                let mut sys = System::new_all();
-                sys.refresh_all();
-
-                let uptime_secs = sysinfo::System::uptime();
-                let uptime = format!(
-                    "{}h {}m {}s",
-                    uptime_secs / 3600,
-                    (uptime_secs % 3600) / 60,
-                    uptime_secs % 60
-                );
-
-                let cpu_name = sys.cpus().first().map(|c| c.brand().to_string()).unwrap_or_default();
-                let cpu_percentage = sys.global_cpu_info().cpu_usage();
-
-                let total_memory = sys.total_memory();
-                let used_memory = sys.used_memory();
-                let memory_percentage = (used_memory as f32 / total_memory as f32) * 100.0;
-
-                let public_ip = ureq::get("https://api.ipify.org")
-                    .call()
-                    .ok()
-                    .and_then(|res| res.into_string().ok())
-                    .unwrap_or_else(|| "Unavailable".to_string());
-
-                let ping_ms = get_ping_ms();
-
-                let (speed_download_mbps, speed_upload_mbps) = match get_speedtest() {
-                    Some((d, u)) => (Some(d), Some(u)),
-                    None => (None, None),
-                };
-
-                let interfaces = get_network_interfaces();
-
-                let all_temps = get_all_temps();
-
-                let response = StatusResponse {
-                    server_status: "online".to_string(),
-                    server_uptime: uptime,
-                    server_data: ServerData {
-                        server_name: sysinfo::System::host_name(),
-                        server_cpu: cpu_name,
-                        server_os: sysinfo::System::name(),
-                    },
-                    data: UsageData {
-                        cpu_percentage,
-                        memory: used_memory as f32 / (1024.0 * 1024.0 * 1024.0),
-                        total_memory: total_memory as f32 / (1024.0 * 1024.0 * 1024.0),
-                        memory_percentage,
-                        temps: all_temps,
-                    },
-                    network: NetworkData {
-                        public_ip,
-                        ping_ms,
-                        speed_download_mbps,
-                        speed_upload_mbps,
-                        interfaces,
-                    },
-                };
-
-                return HttpResponse::Ok().json(response);
+/// Parses a configured token hash, exiting on malformed input rather than
+/// starting with a token nobody can present.
+fn token_hash(key: &str, encoded: &str) -> TokenHash {
+    TokenHash::parse(encoded).unwrap_or_else(|e| {
+        eprintln!("Invalid {}: {}", key, e);
+        process::exit(1);
+    })
+}
+
+/// Command line: an optional subcommand plus the `--insecure` flag.
+struct Args {
+    command: Option<String>,
+    insecure: bool,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = Args { command: None, insecure: false };
+        for arg in env::args().skip(1) {
+            match arg.as_str() {
+                "--insecure" => args.insecure = true,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option {:?}", flag)),
+                _ if args.command.is_some() => return Err(format!("Unexpected argument {:?}", arg)),
+                _ => args.command = Some(arg),
             }
         }
+        Ok(args)
     }
+}
+
+/// Handles the `generate-token` and `hash-token` subcommands. Returns
+/// `false` when the server should start instead.
+fn run_subcommand(command: Option<&str>) -> Result<bool, String> {
+    match command {
+        None => Ok(false),
+        Some("generate-token") => {
+            let token = auth::generate_token();
+            let hash = TokenHash::new(&token);
+            println!("token:       {}", token);
+            println!("hash:        {}", hash);
+            println!("fingerprint: {}", hash.fingerprint);
+            Ok(true)
+        }
+        Some("hash-token") => {
+            // Read from stdin so the token doesn't end up in shell history.
+            let mut token = String::new();
+            std::io::stdin().read_line(&mut token).map_err(|e| e.to_string())?;
+            println!("{}", TokenHash::new(token.trim_end_matches(['\r', '\n'])));
+            Ok(true)
+        }
+        Some(other) => Err(format!("Unknown command {:?}; expected generate-token or hash-token", other)),
+    }
+}
 
-    HttpResponse::Unauthorized().body("Unauthorized")
+struct HttpsPort(u16);
+
+/// Sends plain HTTP requests to the same host and path over HTTPS. Uses 308
+/// so clients repeat POSTs instead of turning them into GETs.
+async fn redirect_to_https(req: HttpRequest, https_port: web::Data<HttpsPort>) -> HttpResponse {
+    let host = req.connection_info().host().to_string();
+    let host = match host.strip_prefix('[') {
+        Some(rest) => format!("[{}]", rest.split(']').next().unwrap_or_default()),
+        None => host.split(':').next().unwrap_or_default().to_string(),
+    };
+    let port = if https_port.0 == 443 { String::new() } else { format!(":{}", https_port.0) };
+    let path = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/");
+
+    HttpResponse::PermanentRedirect()
+        .insert_header(("Location", format!("https://{}{}{}", host, port, path)))
+        .finish()
+}
+
+/// Attaches the verified client certificate of a TLS connection, if any, so
+/// `Tokens::authorize` can map it to a grant.
+fn attach_client_cert(conn: &dyn Any, data: &mut Extensions) {
+    let Some(stream) = conn.downcast_ref::<TlsStream<TcpStream>>() else { return };
+    let (_, session) = stream.get_ref();
+    if let Some(cert) = session.peer_certificates().and_then(|chain| chain.first()).and_then(|leaf| ClientCert::from_der(leaf)) {
+        data.insert(cert);
+    }
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let args = Args::parse().and_then(|args| run_subcommand(args.command.as_deref()).map(|done| (args, done)));
+    let args = match args {
+        Ok((_, true)) => return Ok(()),
+        Ok((args, false)) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+
     dotenv().ok();
 
     let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let token = env::var("BEARER_TOKEN").unwrap_or_else(|_| "default-token".to_string());
-
-    println!("Loaded token: {}", token);
+    let tls = TlsSettings::from_env().unwrap_or_else(|e| {
+        eprintln!("Invalid TLS configuration: {}", e);
+        process::exit(1);
+    });
+    let tls_config = tls.as_ref().map(|tls| {
+        tls.server_config().unwrap_or_else(|e| {
+            eprintln!("Failed to load TLS certificate: {}", e);
+            process::exit(1);
+        })
+    });
+    let redirect_port = env::var("HTTP_REDIRECT_PORT").ok().filter(|p| !p.is_empty());
+    if redirect_port.is_some() && tls.is_none() {
+        eprintln!("HTTP_REDIRECT_PORT is ignored without TLS_CERT_FILE and TLS_KEY_FILE");
+    }
+    let bearer = match env::var("BEARER_TOKEN_HASH").ok().filter(|h| !h.is_empty()) {
+        Some(hash) => Some(token_hash("BEARER_TOKEN_HASH", &hash)),
+        None => env::var("BEARER_TOKEN").ok().filter(|t| !t.is_empty()).map(|t| TokenHash::new(&t)),
+    };
+    let metrics_token = match env::var("METRICS_TOKEN_HASH").ok().filter(|h| !h.is_empty()) {
+        Some(hash) => Some(token_hash("METRICS_TOKEN_HASH", &hash)),
+        None => env::var("METRICS_TOKEN").ok().filter(|t| !t.is_empty()).map(|t| TokenHash::new(&t)),
+    };
+
+    let registry: Vec<ApiToken> = config::json_file_from_env("TOKENS_FILE").unwrap_or_default();
+    println!("🔑 Loaded {} API token(s) from the registry", registry.len());
+    if !args.insecure {
+        let key = |name: &str| match env::var(format!("{}_HASH", name)).is_ok_and(|h| !h.is_empty()) {
+            true => format!("{}_HASH", name),
+            false => name.to_string(),
+        };
+        let sources = [(key("BEARER_TOKEN"), bearer.as_ref()), (key("METRICS_TOKEN"), metrics_token.as_ref())]
+            .into_iter()
+            .chain(registry.iter().map(|t| (format!("TOKENS_FILE entry {}", t.access.name), Some(&t.hash))));
+        let mut refused = false;
+        for (source, hash) in sources {
+            if let Some(placeholder) = hash.and_then(TokenHash::placeholder) {
+                eprintln!("{} is the placeholder {:?}; refusing to start.", source, placeholder);
+                refused = true;
+            }
+        }
+        if refused {
+            eprintln!("Generate tokens with `status_server generate-token`, or pass --insecure to start anyway.");
+            process::exit(1);
+        }
+    }
+    let cert_mappings: Vec<CertMapping> = match tls.as_ref().and_then(|tls| tls.client_ca_file.as_ref()) {
+        Some(_) => config::json_file_from_env("CLIENT_CERTS_FILE").unwrap_or_default(),
+        None => {
+            if env::var("CLIENT_CERTS_FILE").is_ok_and(|f| !f.is_empty()) {
+                eprintln!("CLIENT_CERTS_FILE is ignored without TLS_CLIENT_CA_FILE");
+            }
+            Vec::new()
+        }
+    };
+    if !cert_mappings.is_empty() {
+        println!("🪪 Loaded {} client certificate mapping(s)", cert_mappings.len());
+    }
+    let bearer = match bearer {
+        None if registry.is_empty() && cert_mappings.is_empty() && !args.insecure => {
+            eprintln!(
+                "No BEARER_TOKEN, BEARER_TOKEN_HASH, TOKENS_FILE or CLIENT_CERTS_FILE entries configured; refusing to start. \
+                 Generate a token with `status_server generate-token`, or pass --insecure to fall back to \
+                 the well-known token \"default-token\"."
+            );
+            process::exit(1);
+        }
+        None if registry.is_empty() && cert_mappings.is_empty() => Some(TokenHash::new("default-token")),
+        bearer => bearer,
+    };
+    let tokens = web::Data::new(Tokens::new(bearer, metrics_token, registry, cert_mappings));
+    let access_log = env::var("ACCESS_LOG").map(|v| v != "false").unwrap_or(true);
+
+    let server_data = web::Data::new(collectors::get_server_data());
+    let history_capacity = env::var("HISTORY_CAPACITY").ok().and_then(|v| v.parse().ok()).unwrap_or(3600);
+    let data_dir = env::var("DATA_DIR").ok().filter(|d| !d.is_empty());
+    let store = data_dir.as_ref().and_then(|dir| {
+        match Store::open(Path::new(dir), Retention::from_env()) {
+            Ok(store) => {
+                println!("💾 Persisting metrics under {}", dir);
+                let store = Arc::new(store);
+                store::spawn_pruner(&store);
+                Some(store)
+            }
+            Err(e) => {
+                eprintln!("Failed to open metric store in {}: {}", dir, e);
+                None
+            }
+        }
+    });
+    let history: SharedHistory = Arc::new(History::new(history_capacity, store));
+    let rules: Vec<Rule> = config::json_file_from_env("ALERT_RULES_FILE").unwrap_or_default();
+    for rule in &rules {
+        if let Err(e) = rule.validate() {
+            eprintln!("Invalid alert rule {}: {}", rule.name, e);
+            process::exit(1);
+        }
+    }
+    println!("🔔 Loaded {} alert rule(s)", rules.len());
+    let engine: SharedAlerts = Arc::new(RwLock::new(AlertEngine::new(rules)));
+
+    let destinations: Vec<Destination> = config::json_file_from_env("NOTIFIERS_FILE").unwrap_or_default();
+    println!("📣 Loaded {} notification destination(s)", destinations.len());
+    let delivery_log = SharedDeliveryLog::default();
+    let notifier = Notifier::spawn(destinations, &delivery_log);
+
+    let intervals = Intervals::from_env();
+    let updaters: Vec<Updater> = config::json_file_from_env("DDNS_FILE").unwrap_or_default();
+    println!("🌍 Loaded {} DDNS updater(s)", updaters.len());
+    let ddns = Ddns::new(updaters);
+    let ddns_status = web::Data::new(ddns.status());
+    let ip_monitor = IpMonitor::new(data_dir.as_deref().map(Path::new), &notifier, ddns);
+    let ip_events = web::Data::new(ip_monitor.events());
+    let sampler = sampler::spawn(&intervals, &history, &engine, &notifier, ip_monitor);
+    let speedtest_history = env::var("SPEEDTEST_HISTORY").ok().and_then(|v| v.parse().ok()).unwrap_or(50);
+    let speedtests = web::Data::new(Speedtests::spawn(&sampler, Backend::from_env(), intervals.speedtest, speedtest_history));
+    let snapshot = web::Data::new(sampler.snapshot);
+    let throughput_server = web::Data::new(ThroughputServer(
+        env::var("THROUGHPUT_SERVER").map(|v| v == "true").unwrap_or(false),
+    ));
+    if throughput_server.0 {
+        println!("📶 Serving throughput tests on /throughput");
+    }
+    let tcp_throughput = match env::var("THROUGHPUT_TCP_LISTEN").ok().filter(|l| !l.is_empty()) {
+        // The raw protocol sends tokens in cleartext and can't check client
+        // certificates, so it would undo what the TLS settings protect.
+        Some(listen) if tls.is_some() => {
+            eprintln!(
+                "THROUGHPUT_TCP_LISTEN is ignored with TLS configured: the raw TCP protocol sends tokens \
+                 in cleartext. Use THROUGHPUT_SERVER=true to serve the tests over HTTPS instead of {}",
+                listen
+            );
+            None
+        }
+        Some(listen) => match throughput::spawn_tcp_server(&listen, tokens.clone().into_inner()) {
+            Ok(()) => {
+                println!("📶 Serving raw TCP throughput tests on {}", listen);
+                Some(listen)
+            }
+            Err(e) => {
+                eprintln!("Failed to listen for throughput tests on {}: {}", listen, e);
+                None
+            }
+        },
+        None => None,
+    };
+    let delivery_log = web::Data::new(delivery_log);
+    let engine = web::Data::new(engine);
+    let history = web::Data::new(history);
+
+    println!("🔒 Security summary:");
+    for line in tokens.summary() {
+        println!("   {}", line);
+    }
+    match &tls {
+        Some(tls) => println!(
+            "   listening on 0.0.0.0:{} over HTTPS (TLS {}+, certificate {})",
+            port,
+            tls.min_version_str(),
+            tls.cert_file.display()
+        ),
+        None => println!("   listening on 0.0.0.0:{} over plain HTTP; tokens cross the network unencrypted", port),
+    }
+    if let Some(ca_file) = tls.as_ref().and_then(|tls| tls.client_ca_file.as_ref()) {
+        let mode = if tls.as_ref().is_some_and(|tls| tls.client_auth_required) {
+            "required; connections without one are refused"
+        } else {
+            "optional; bearer tokens are still accepted"
+        };
+        println!("   client certificates signed by {} ({})", ca_file.display(), mode);
+    }
+    if let (Some(_), Some(redirect_port)) = (&tls, &redirect_port) {
+        println!("   redirecting plain HTTP on 0.0.0.0:{} to HTTPS", redirect_port);
+    }
+    if throughput_server.0 || tcp_throughput.is_some() {
+        println!("   throughput test server enabled for tokens with trigger-jobs");
+    }
+    if let Some(listen) = &tcp_throughput {
+        println!("   ⚠️  raw TCP throughput listener on {} receives tokens in cleartext", listen);
+    }
+    if args.insecure {
+        println!("   ⚠️  started with --insecure: placeholder or missing tokens are accepted");
+    }
 
-    println!("🚀 Server running on http://localhost:{}", port);
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    println!("🚀 Server running on {}://localhost:{}", scheme, port);
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
-            .app_data(web::Data::new(token.clone()))
+            .app_data(tokens.clone())
+            .app_data(snapshot.clone())
+            .app_data(server_data.clone())
+            .app_data(history.clone())
+            .app_data(engine.clone())
+            .app_data(delivery_log.clone())
+            .app_data(speedtests.clone())
+            .app_data(throughput_server.clone())
+            .app_data(ip_events.clone())
+            .app_data(ddns_status.clone())
+            .wrap_fn(move |req, srv| {
+                let started = Instant::now();
+                let method = req.method().clone();
+                let path = req.path().to_string();
+                let response = srv.call(req);
+                async move {
+                    let response = response.await?;
+                    if access_log {
+                        let identity = response
+                            .request()
+                            .extensions()
+                            .get::<Authenticated>()
+                            .map(|a| a.0.clone())
+                            .unwrap_or_else(|| "-".to_string());
+                        println!(
+                            "{} {} {} {}ms {}",
+                            method,
+                            path,
+                            response.status().as_u16(),
+                            started.elapsed().as_millis(),
+                            identity
+                        );
+                    }
+                    Ok(response)
+                }
+            })
             .service(status)
+            .service(prometheus_metrics)
+            .service(sensor_inventory)
+            .service(history_query)
+            .service(active_alerts)
+            .service(notification_log)
+            .service(speedtest_jobs)
+            .service(speedtest_job)
+            .service(trigger_speedtest)
+            .service(public_ip_events)
+            .service(ddns_report)
+            .service(throughput_ping)
+            .service(throughput_download)
+            .service(throughput_upload)
         })
-        .bind(format!("0.0.0.0:{}", port))?
-        .run()
-        .await
+        .on_connect(attach_client_cert);
+    let server = match tls_config {
+        Some(config) => server.bind_rustls_0_23(format!("0.0.0.0:{}", port), config)?,
+        None => server.bind(format!("0.0.0.0:{}", port))?,
+    }
+    .run();
+
+    match redirect_port.filter(|_| tls.is_some()) {
+        Some(redirect_port) => {
+            let https_port = web::Data::new(HttpsPort(port.parse().unwrap_or(443)));
+            let redirect = HttpServer::new(move || {
+                App::new().app_data(https_port.clone()).default_service(web::to(redirect_to_https))
+            })
+            .bind(format!("0.0.0.0:{}", redirect_port))?
+            .run();
+            futures_util::future::try_join(server, redirect).await.map(|_| ())
+        }
+        None => server.await,
+    }
 }
-
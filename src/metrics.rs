@@ -0,0 +1,427 @@
+use std::fmt::Write;
+use std::time::UNIX_EPOCH;
+
+use sysinfo::System;
+
+use crate::diskio::DiskIo;
+use crate::disks::Filesystem;
+use crate::hwmon::SensorKind;
+use crate::models::ServerData;
+use crate::netif::NetworkInterface;
+use crate::probes::ProbeResult;
+use crate::sampler::{Sampled, Snapshot};
+
+const PREFIX: &str = "status_server";
+
+/// Renders the snapshot in the Prometheus text exposition format (v0.0.4).
+pub fn render(snapshot: &Snapshot, server_data: &ServerData) -> String {
+    let mut out = Exposition::default();
+
+    out.family("info", "gauge", "Static host information, always 1.");
+    out.sample("info", &[
+        ("hostname", server_data.server_name.as_deref().unwrap_or("")),
+        ("cpu", &server_data.server_cpu),
+        ("os", server_data.server_os.as_deref().unwrap_or("")),
+    ], 1.0);
+
+    out.family("uptime_seconds", "gauge", "Host uptime in seconds.");
+    out.sample("uptime_seconds", &[], System::uptime() as f64);
+
+    if let Some(usage) = &snapshot.usage {
+        let usage = &usage.value;
+
+        out.family("cpu_usage_percent", "gauge", "Global CPU utilisation since the previous sample.");
+        out.sample("cpu_usage_percent", &[], usage.cpu_percentage as f64);
+
+        out.family("cpu_core_usage_percent", "gauge", "Per-core CPU utilisation since the previous sample.");
+        for (core, value) in usage.cpu_per_core.iter().enumerate() {
+            out.sample("cpu_core_usage_percent", &[("core", &core.to_string())], *value as f64);
+        }
+
+        out.family("cpu_usage_average_percent", "gauge", "Time-weighted CPU utilisation over a trailing window.");
+        out.sample("cpu_usage_average_percent", &[("window", "1s")], usage.cpu_average.last_1s as f64);
+        out.sample("cpu_usage_average_percent", &[("window", "10s")], usage.cpu_average.last_10s as f64);
+        out.sample("cpu_usage_average_percent", &[("window", "60s")], usage.cpu_average.last_60s as f64);
+
+        out.family("memory_used_bytes", "gauge", "Used physical memory in bytes.");
+        out.sample("memory_used_bytes", &[], usage.used_memory as f64);
+
+        out.family("memory_total_bytes", "gauge", "Total physical memory in bytes.");
+        out.sample("memory_total_bytes", &[], usage.total_memory as f64);
+    }
+
+    if let Some(sensors) = &snapshot.sensors {
+        let temps = &sensors.value.temps;
+
+        out.family("temperature_celsius", "gauge", "Temperature reading in degrees Celsius.");
+        for sensor in &temps.sensors {
+            let label = sensor.label.as_deref().unwrap_or(&sensor.channel);
+            out.sample("temperature_celsius", &[("chip", &sensor.chip), ("device", &sensor.device), ("sensor", label)], sensor.celsius as f64);
+        }
+
+        out.family("temperature_max_celsius", "gauge", "High threshold of a temperature sensor in degrees Celsius.");
+        for sensor in &temps.sensors {
+            if let Some(max) = sensor.max {
+                let label = sensor.label.as_deref().unwrap_or(&sensor.channel);
+                out.sample("temperature_max_celsius", &[("chip", &sensor.chip), ("device", &sensor.device), ("sensor", label)], max as f64);
+            }
+        }
+
+        out.family("temperature_critical_celsius", "gauge", "Critical threshold of a temperature sensor in degrees Celsius.");
+        for sensor in &temps.sensors {
+            if let Some(critical) = sensor.critical {
+                let label = sensor.label.as_deref().unwrap_or(&sensor.channel);
+                out.sample("temperature_critical_celsius", &[("chip", &sensor.chip), ("device", &sensor.device), ("sensor", label)], critical as f64);
+            }
+        }
+
+        for (kind, name, help) in [
+            (SensorKind::Fan, "fan_rpm", "Fan speed in revolutions per minute."),
+            (SensorKind::Voltage, "voltage_volts", "Voltage reading in volts."),
+            (SensorKind::Current, "current_amperes", "Current reading in amperes."),
+            (SensorKind::Power, "power_watts", "Power draw in watts."),
+        ] {
+            out.family(name, "gauge", help);
+            for chip in &sensors.value.chips {
+                for sensor in chip.sensors.iter().filter(|s| s.kind == kind) {
+                    out.sample(name, &[("chip", &chip.name), ("device", &chip.device), ("sensor", sensor.display_name())], sensor.value);
+                }
+            }
+        }
+
+        out.family("temperature_role_celsius", "gauge", "Summary temperature for each configured role (motherboard, cpu, gpu, ...).");
+        let fixed = [
+            ("motherboard_temp", temps.motherboard_temp),
+            ("cpu_temp", temps.cpu_temp),
+            ("gpu_temp", temps.gpu_temp),
+        ];
+        for (role, value) in fixed.into_iter().chain(temps.extra.iter().map(|(r, &v)| (r.as_str(), v))) {
+            if let Some(value) = value {
+                out.sample("temperature_role_celsius", &[("role", role.trim_end_matches("_temp"))], value as f64);
+            }
+        }
+    }
+
+    if let Some(probes) = &snapshot.probes {
+        let gauges: [ProbeGauge; 7] = [
+            ("probe_rtt_min_seconds", "Fastest round trip of the latest probe round.", |p| p.min_ms.map(|v| v / 1000.0)),
+            ("probe_rtt_avg_seconds", "Mean round trip of the latest probe round.", |p| p.avg_ms.map(|v| v / 1000.0)),
+            ("probe_rtt_max_seconds", "Slowest round trip of the latest probe round.", |p| p.max_ms.map(|v| v / 1000.0)),
+            ("probe_rtt_mdev_seconds", "Standard deviation of round trips in the latest probe round.", |p| p.mdev_ms.map(|v| v / 1000.0)),
+            ("probe_jitter_seconds", "Mean difference between consecutive round trips.", |p| p.jitter_ms.map(|v| v / 1000.0)),
+            ("probe_packet_loss_percent", "Share of probes without a reply in the latest round.", |p| Some(p.loss_percentage)),
+            ("probe_success", "Whether at least one probe in the latest round succeeded.", |p| Some(if p.received > 0 { 1.0 } else { 0.0 })),
+        ];
+
+        for (name, help, value) in gauges {
+            out.family(name, "gauge", help);
+            for probe in &probes.value {
+                if let Some(value) = value(probe) {
+                    out.sample(name, &[("target", &probe.target), ("kind", probe.kind)], value);
+                }
+            }
+        }
+
+        out.family("probe_http_phase_seconds", "gauge", "Duration of each phase of the latest HTTP probe.");
+        for probe in &probes.value {
+            let Some(http) = &probe.http else { continue };
+            for (phase, ms) in [
+                ("dns", http.dns_ms),
+                ("connect", http.connect_ms),
+                ("tls", http.tls_ms),
+                ("ttfb", http.ttfb_ms),
+                ("total", http.total_ms),
+            ] {
+                if let Some(ms) = ms {
+                    out.sample("probe_http_phase_seconds", &[("target", &probe.target), ("phase", phase)], ms / 1000.0);
+                }
+            }
+        }
+
+        out.family("probe_http_status_code", "gauge", "HTTP status code returned to the latest HTTP probe.");
+        for probe in &probes.value {
+            if let Some(code) = probe.http.as_ref().and_then(|h| h.status_code) {
+                out.sample("probe_http_status_code", &[("target", &probe.target)], code as f64);
+            }
+        }
+    }
+
+    if let Some(speedtest) = &snapshot.speedtest {
+        let result = &speedtest.value;
+        out.family("speedtest_download_bits_per_second", "gauge", "Download throughput of the latest speed test.");
+        out.sample("speedtest_download_bits_per_second", &[], result.download_mbps * 1_000_000.0);
+
+        out.family("speedtest_upload_bits_per_second", "gauge", "Upload throughput of the latest speed test.");
+        out.sample("speedtest_upload_bits_per_second", &[], result.upload_mbps * 1_000_000.0);
+
+        if let Some(latency) = result.latency_ms {
+            out.family("speedtest_latency_seconds", "gauge", "Latency measured by the latest speed test.");
+            out.sample("speedtest_latency_seconds", &[], latency / 1000.0);
+        }
+    }
+
+    if let Some(interfaces) = &snapshot.interfaces {
+        out.family("network_interface_info", "gauge", "Link details of a network interface, always 1.");
+        for iface in &interfaces.value {
+            out.sample("network_interface_info", &[
+                ("interface", &iface.name),
+                ("mac", iface.mac.as_deref().unwrap_or("")),
+                ("operstate", iface.operstate.as_deref().unwrap_or("unknown")),
+            ], 1.0);
+        }
+
+        out.family("network_interface_address_info", "gauge", "Address assigned to a network interface, always 1.");
+        for iface in &interfaces.value {
+            for addr in &iface.addresses {
+                out.sample("network_interface_address_info", &[
+                    ("interface", &iface.name),
+                    ("address", &addr.ip),
+                    ("prefix_len", &addr.prefix_len.to_string()),
+                    ("family", addr.family),
+                ], 1.0);
+            }
+        }
+
+        out.family("network_mtu_bytes", "gauge", "MTU of a network interface.");
+        for iface in &interfaces.value {
+            if let Some(mtu) = iface.mtu {
+                out.sample("network_mtu_bytes", &[("interface", &iface.name)], mtu as f64);
+            }
+        }
+
+        out.family("network_speed_bits_per_second", "gauge", "Negotiated link speed of a network interface.");
+        for iface in &interfaces.value {
+            if let Some(speed) = iface.speed_mbps {
+                out.sample("network_speed_bits_per_second", &[("interface", &iface.name)], speed as f64 * 1_000_000.0);
+            }
+        }
+
+        let gauges: [InterfaceGauge; 12] = [
+            ("network_receive_bytes_total", "counter", "Bytes received.", |i| i.rx_bytes as f64),
+            ("network_transmit_bytes_total", "counter", "Bytes transmitted.", |i| i.tx_bytes as f64),
+            ("network_receive_packets_total", "counter", "Packets received.", |i| i.rx_packets as f64),
+            ("network_transmit_packets_total", "counter", "Packets transmitted.", |i| i.tx_packets as f64),
+            ("network_receive_errors_total", "counter", "Receive errors.", |i| i.rx_errors as f64),
+            ("network_transmit_errors_total", "counter", "Transmit errors.", |i| i.tx_errors as f64),
+            ("network_receive_drop_total", "counter", "Received packets dropped.", |i| i.rx_dropped as f64),
+            ("network_transmit_drop_total", "counter", "Transmitted packets dropped.", |i| i.tx_dropped as f64),
+            ("network_receive_bytes_per_second", "gauge", "Receive throughput since the previous sample.", |i| i.rx_bytes_per_sec),
+            ("network_transmit_bytes_per_second", "gauge", "Transmit throughput since the previous sample.", |i| i.tx_bytes_per_sec),
+            ("network_receive_packets_per_second", "gauge", "Receive packet rate since the previous sample.", |i| i.rx_packets_per_sec),
+            ("network_transmit_packets_per_second", "gauge", "Transmit packet rate since the previous sample.", |i| i.tx_packets_per_sec),
+        ];
+
+        for (name, kind, help, value) in gauges {
+            out.family(name, kind, help);
+            for iface in &interfaces.value {
+                out.sample(name, &[("interface", &iface.name)], value(iface));
+            }
+        }
+    }
+
+    if let Some(disks) = &snapshot.disks {
+        let gauges: [FilesystemGauge; 6] = [
+            ("filesystem_size_bytes", "Filesystem size in bytes.", |fs| fs.total_bytes as f64),
+            ("filesystem_used_bytes", "Used filesystem space in bytes.", |fs| fs.used_bytes as f64),
+            ("filesystem_avail_bytes", "Filesystem space available to unprivileged users in bytes.", |fs| fs.available_bytes as f64),
+            ("filesystem_files", "Total inodes.", |fs| fs.total_inodes as f64),
+            ("filesystem_files_used", "Used inodes.", |fs| fs.used_inodes as f64),
+            ("filesystem_readonly", "Whether the filesystem is mounted read-only.", |fs| if fs.read_only { 1.0 } else { 0.0 }),
+        ];
+
+        for (name, help, value) in gauges {
+            out.family(name, "gauge", help);
+            for fs in &disks.value {
+                out.sample(name, &[("device", &fs.device), ("mountpoint", &fs.mount_point), ("fstype", &fs.fs_type)], value(fs));
+            }
+        }
+    }
+
+    if let Some(disk_io) = &snapshot.disk_io {
+        let gauges: [DiskIoGauge; 10] = [
+            ("disk_read_bytes_total", "counter", "Bytes read from the device.", |io| io.read_bytes_total as f64),
+            ("disk_written_bytes_total", "counter", "Bytes written to the device.", |io| io.write_bytes_total as f64),
+            ("disk_reads_completed_total", "counter", "Read requests completed.", |io| io.reads_total as f64),
+            ("disk_writes_completed_total", "counter", "Write requests completed.", |io| io.writes_total as f64),
+            ("disk_read_bytes_per_second", "gauge", "Read throughput since the previous sample.", |io| io.read_bytes_per_sec),
+            ("disk_write_bytes_per_second", "gauge", "Write throughput since the previous sample.", |io| io.write_bytes_per_sec),
+            ("disk_reads_per_second", "gauge", "Read IOPS since the previous sample.", |io| io.read_iops),
+            ("disk_writes_per_second", "gauge", "Write IOPS since the previous sample.", |io| io.write_iops),
+            ("disk_await_seconds", "gauge", "Mean request latency since the previous sample.", |io| io.await_ms / 1000.0),
+            ("disk_io_utilization_percent", "gauge", "Share of time the device was busy since the previous sample.", |io| io.utilization_percentage),
+        ];
+
+        for (name, kind, help, value) in gauges {
+            out.family(name, kind, help);
+            for io in &disk_io.value {
+                out.sample(name, &[("device", &io.device)], value(io));
+            }
+        }
+    }
+
+    out.family("sample_timestamp_seconds", "gauge", "Unix time at which each section was last sampled.");
+    for (section, sampled_at) in [
+        ("usage", snapshot.usage.as_ref().map(sampled_at)),
+        ("sensors", snapshot.sensors.as_ref().map(sampled_at)),
+        ("public_ip", snapshot.public_ip.as_ref().map(sampled_at)),
+        ("probes", snapshot.probes.as_ref().map(sampled_at)),
+        ("speedtest", snapshot.speedtest.as_ref().map(sampled_at)),
+        ("interfaces", snapshot.interfaces.as_ref().map(sampled_at)),
+        ("disks", snapshot.disks.as_ref().map(sampled_at)),
+        ("disk_io", snapshot.disk_io.as_ref().map(sampled_at)),
+    ] {
+        if let Some(sampled_at) = sampled_at {
+            out.sample("sample_timestamp_seconds", &[("section", section)], sampled_at);
+        }
+    }
+
+    out.text
+}
+
+type FilesystemGauge = (&'static str, &'static str, fn(&Filesystem) -> f64);
+type InterfaceGauge = (&'static str, &'static str, &'static str, fn(&NetworkInterface) -> f64);
+type ProbeGauge = (&'static str, &'static str, fn(&ProbeResult) -> Option<f64>);
+type DiskIoGauge = (&'static str, &'static str, &'static str, fn(&DiskIo) -> f64);
+
+fn sampled_at<T>(sample: &Sampled<T>) -> f64 {
+    sample.sampled_at.duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or_default()
+}
+
+#[derive(Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP {}_{} {}", PREFIX, name, help);
+        let _ = writeln!(self.text, "# TYPE {}_{} {}", PREFIX, name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let _ = write!(self.text, "{}_{}", PREFIX, name);
+
+        if !labels.is_empty() {
+            let rendered: Vec<String> = labels.iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+                .collect();
+            let _ = write!(self.text, "{{{}}}", rendered.join(","));
+        }
+
+        let _ = writeln!(self.text, " {}", format_value(value));
+    }
+}
+
+/// Rust prints infinities as `inf`; the exposition format spells them `+Inf`
+/// and `-Inf`.
+fn format_value(value: f64) -> String {
+    match value {
+        f64::INFINITY => "+Inf".to_string(),
+        f64::NEG_INFINITY => "-Inf".to_string(),
+        v if v.is_nan() => "NaN".to_string(),
+        v => v.to_string(),
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::speedtest::SpeedResult;
+
+    fn sampled<T>(value: T) -> Option<Sampled<T>> {
+        Some(Sampled { value, sampled_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000) })
+    }
+
+    #[test]
+    fn renders_families_in_the_exposition_format() {
+        let snapshot = Snapshot {
+            probes: sampled(vec![
+                ProbeResult::from_rtts("8.8.8.8", "icmp", 2, &[10.0, 20.0], None),
+                ProbeResult::from_rtts("https://example.com/\"q\"", "http", 2, &[], Some("HTTP 500".to_string())),
+            ]),
+            speedtest: sampled(SpeedResult { download_mbps: 94.5, upload_mbps: 12.25, latency_ms: None }),
+            ..Snapshot::default()
+        };
+        let server_data = ServerData {
+            server_name: Some("rack\\1 \"edge\"\nb".to_string()),
+            server_cpu: "Test CPU".to_string(),
+            server_os: None,
+        };
+
+        // Uptime comes from the host, so it's the one line left out.
+        let text = render(&snapshot, &server_data);
+        let rendered: Vec<&str> = text
+            .lines()
+            .filter(|line| !line.starts_with("status_server_uptime_seconds "))
+            .collect();
+
+        let expected = r#"# HELP status_server_info Static host information, always 1.
+# TYPE status_server_info gauge
+status_server_info{hostname="rack\\1 \"edge\"\nb",cpu="Test CPU",os=""} 1
+# HELP status_server_uptime_seconds Host uptime in seconds.
+# TYPE status_server_uptime_seconds gauge
+# HELP status_server_probe_rtt_min_seconds Fastest round trip of the latest probe round.
+# TYPE status_server_probe_rtt_min_seconds gauge
+status_server_probe_rtt_min_seconds{target="8.8.8.8",kind="icmp"} 0.01
+# HELP status_server_probe_rtt_avg_seconds Mean round trip of the latest probe round.
+# TYPE status_server_probe_rtt_avg_seconds gauge
+status_server_probe_rtt_avg_seconds{target="8.8.8.8",kind="icmp"} 0.015
+# HELP status_server_probe_rtt_max_seconds Slowest round trip of the latest probe round.
+# TYPE status_server_probe_rtt_max_seconds gauge
+status_server_probe_rtt_max_seconds{target="8.8.8.8",kind="icmp"} 0.02
+# HELP status_server_probe_rtt_mdev_seconds Standard deviation of round trips in the latest probe round.
+# TYPE status_server_probe_rtt_mdev_seconds gauge
+status_server_probe_rtt_mdev_seconds{target="8.8.8.8",kind="icmp"} 0.005
+# HELP status_server_probe_jitter_seconds Mean difference between consecutive round trips.
+# TYPE status_server_probe_jitter_seconds gauge
+status_server_probe_jitter_seconds{target="8.8.8.8",kind="icmp"} 0.01
+# HELP status_server_probe_packet_loss_percent Share of probes without a reply in the latest round.
+# TYPE status_server_probe_packet_loss_percent gauge
+status_server_probe_packet_loss_percent{target="8.8.8.8",kind="icmp"} 0
+status_server_probe_packet_loss_percent{target="https://example.com/\"q\"",kind="http"} 100
+# HELP status_server_probe_success Whether at least one probe in the latest round succeeded.
+# TYPE status_server_probe_success gauge
+status_server_probe_success{target="8.8.8.8",kind="icmp"} 1
+status_server_probe_success{target="https://example.com/\"q\"",kind="http"} 0
+# HELP status_server_probe_http_phase_seconds Duration of each phase of the latest HTTP probe.
+# TYPE status_server_probe_http_phase_seconds gauge
+# HELP status_server_probe_http_status_code HTTP status code returned to the latest HTTP probe.
+# TYPE status_server_probe_http_status_code gauge
+# HELP status_server_speedtest_download_bits_per_second Download throughput of the latest speed test.
+# TYPE status_server_speedtest_download_bits_per_second gauge
+status_server_speedtest_download_bits_per_second 94500000
+# HELP status_server_speedtest_upload_bits_per_second Upload throughput of the latest speed test.
+# TYPE status_server_speedtest_upload_bits_per_second gauge
+status_server_speedtest_upload_bits_per_second 12250000
+# HELP status_server_sample_timestamp_seconds Unix time at which each section was last sampled.
+# TYPE status_server_sample_timestamp_seconds gauge
+status_server_sample_timestamp_seconds{section="probes"} 1700000000
+status_server_sample_timestamp_seconds{section="speedtest"} 1700000000"#;
+
+        assert_eq!(rendered, expected.lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn writes_non_finite_values_as_prometheus_spells_them() {
+        let mut out = Exposition::default();
+        out.family("values", "gauge", "Test values.");
+        for (case, value) in [("pos", f64::INFINITY), ("neg", f64::NEG_INFINITY), ("nan", f64::NAN), ("small", -0.25)] {
+            out.sample("values", &[("case", case)], value);
+        }
+
+        assert_eq!(out.text, "\
+# HELP status_server_values Test values.
+# TYPE status_server_values gauge
+status_server_values{case=\"pos\"} +Inf
+status_server_values{case=\"neg\"} -Inf
+status_server_values{case=\"nan\"} NaN
+status_server_values{case=\"small\"} -0.25
+");
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::diskio::DiskIo;
+use crate::disks::Filesystem;
+use crate::hwmon::{Chip, TempReading};
+use crate::netif::NetworkInterface;
+use crate::probes::ProbeResult;
+
+#[derive(Serialize, Clone, Default)]
+pub struct TempData {
+    pub motherboard_temp: Option<f32>,
+    pub cpu_temp: Option<f32>,
+    pub gpu_temp: Option<f32>,
+    /// Additional roles from `TEMP_ROLES_FILE`, e.g. `nvme_temp`.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Option<f32>>,
+    pub sensors: Vec<TempReading>,
+    pub sampled_at: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ServerData {
+    pub server_name: Option<String>,
+    pub server_cpu: String,
+    pub server_os: Option<String>,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct CpuAverages {
+    pub last_1s: f32,
+    pub last_10s: f32,
+    pub last_60s: f32,
+}
+
+#[derive(Serialize)]
+pub struct UsageData {
+    pub cpu_percentage: f32,
+    pub cpu_per_core: Vec<f32>,
+    pub cpu_average: CpuAverages,
+    pub memory: f32,
+    pub total_memory: f32,
+    pub memory_percentage: f32,
+    pub temps: TempData,
+    pub sampled_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct NetworkTimestamps {
+    pub public_ip: Option<String>,
+    pub probes: Option<String>,
+    pub speedtest: Option<String>,
+    pub interfaces: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct NetworkData {
+    /// IPv4 address if known, otherwise IPv6; `null` when neither is.
+    pub public_ip: Option<String>,
+    pub public_ipv4: Option<String>,
+    pub public_ipv6: Option<String>,
+    pub ping_ms: Option<f64>,
+    pub probes: Vec<ProbeResult>,
+    pub speed_download_mbps: Option<f64>,
+    pub speed_upload_mbps: Option<f64>,
+    pub speed_latency_ms: Option<f64>,
+    pub interfaces: Vec<NetworkInterface>,
+    pub sampled_at: NetworkTimestamps,
+}
+
+#[derive(Serialize)]
+pub struct DiskTimestamps {
+    pub filesystems: Option<String>,
+    pub io: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DiskData {
+    pub filesystems: Vec<Filesystem>,
+    pub io: Vec<DiskIo>,
+    pub sampled_at: DiskTimestamps,
+}
+
+#[derive(Serialize)]
+pub struct StatusResponse {
+    pub server_status: String,
+    pub server_uptime: String,
+    pub server_data: ServerData,
+    pub data: UsageData,
+    pub network: NetworkData,
+    pub disks: DiskData,
+}
+
+/// Output of the sensor sampler: the raw inventory plus the role summary
+/// derived from it.
+#[derive(Clone)]
+pub struct SensorSample {
+    pub chips: Vec<Chip>,
+    pub temps: TempData,
+}
+
+#[derive(Serialize)]
+pub struct SensorsResponse {
+    pub chips: Vec<Chip>,
+    pub sampled_at: Option<String>,
+}
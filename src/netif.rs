@@ -0,0 +1,132 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use get_if_addrs::{get_if_addrs, IfAddr};
+use serde::Serialize;
+
+#[derive(Serialize, Clone)]
+pub struct InterfaceAddress {
+    pub ip: String,
+    pub prefix_len: u32,
+    pub family: &'static str,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub addresses: Vec<InterfaceAddress>,
+    pub mac: Option<String>,
+    pub mtu: Option<u32>,
+    pub operstate: Option<String>,
+    /// Negotiated link speed; `None` for virtual links and links that are down.
+    pub speed_mbps: Option<u32>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+    pub rx_packets_per_sec: f64,
+    pub tx_packets_per_sec: f64,
+}
+
+/// Combines the addresses from `getifaddrs` with link details and traffic
+/// counters from `/sys/class/net`, computing rates against the previous call.
+pub struct InterfaceCollector {
+    sysfs_root: PathBuf,
+    previous: Option<(Instant, HashMap<String, [u64; 4]>)>,
+}
+
+impl InterfaceCollector {
+    pub fn from_env() -> Self {
+        InterfaceCollector {
+            sysfs_root: PathBuf::from(env::var("SYSFS_ROOT").unwrap_or_else(|_| "/sys".to_string())),
+            previous: None,
+        }
+    }
+
+    pub fn sample(&mut self) -> Vec<NetworkInterface> {
+        let mut interfaces: BTreeMap<String, NetworkInterface> = BTreeMap::new();
+
+        let net_dir = self.sysfs_root.join("class/net");
+        for entry in fs::read_dir(&net_dir).into_iter().flatten().filter_map(Result::ok) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            interfaces.insert(name.clone(), read_link(&entry.path(), name));
+        }
+
+        for iface in get_if_addrs().unwrap_or_default() {
+            let (ip, netmask) = match &iface.addr {
+                IfAddr::V4(v4) => (IpAddr::V4(v4.ip), IpAddr::V4(v4.netmask)),
+                IfAddr::V6(v6) => (IpAddr::V6(v6.ip), IpAddr::V6(v6.netmask)),
+            };
+            let prefix_len = match netmask {
+                IpAddr::V4(mask) => u32::from(mask).count_ones(),
+                IpAddr::V6(mask) => u128::from(mask).count_ones(),
+            };
+
+            interfaces.entry(iface.name.clone())
+                .or_insert_with(|| NetworkInterface { name: iface.name.clone(), ..NetworkInterface::default() })
+                .addresses
+                .push(InterfaceAddress {
+                    ip: ip.to_string(),
+                    prefix_len,
+                    family: if ip.is_ipv4() { "ipv4" } else { "ipv6" },
+                });
+        }
+
+        let now = Instant::now();
+        let counters: HashMap<String, [u64; 4]> = interfaces.values()
+            .map(|i| (i.name.clone(), [i.rx_bytes, i.tx_bytes, i.rx_packets, i.tx_packets]))
+            .collect();
+
+        if let Some((then, previous)) = self.previous.replace((now, counters)) {
+            let secs = now.duration_since(then).as_secs_f64();
+            for iface in interfaces.values_mut().filter(|_| secs > 0.0) {
+                let Some(prev) = previous.get(&iface.name) else {
+                    continue;
+                };
+                // Counters reset when a link is recreated; report that as idle.
+                let rate = |new: u64, old: u64| new.saturating_sub(old) as f64 / secs;
+                iface.rx_bytes_per_sec = rate(iface.rx_bytes, prev[0]);
+                iface.tx_bytes_per_sec = rate(iface.tx_bytes, prev[1]);
+                iface.rx_packets_per_sec = rate(iface.rx_packets, prev[2]);
+                iface.tx_packets_per_sec = rate(iface.tx_packets, prev[3]);
+            }
+        }
+
+        interfaces.into_values().collect()
+    }
+}
+
+fn read_link(dir: &Path, name: String) -> NetworkInterface {
+    let text = |file: &str| {
+        fs::read_to_string(dir.join(file)).ok().map(|t| t.trim().to_string()).filter(|t| !t.is_empty())
+    };
+    let stat = |counter: &str| text(&format!("statistics/{}", counter)).and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    NetworkInterface {
+        mac: text("address").filter(|mac| mac != "00:00:00:00:00:00"),
+        mtu: text("mtu").and_then(|v| v.parse().ok()),
+        operstate: text("operstate"),
+        // The kernel reports -1 (or fails the read) when the speed is unknown.
+        speed_mbps: text("speed").and_then(|v| v.parse::<i64>().ok()).filter(|&s| s > 0).map(|s| s as u32),
+        rx_bytes: stat("rx_bytes"),
+        tx_bytes: stat("tx_bytes"),
+        rx_packets: stat("rx_packets"),
+        tx_packets: stat("tx_packets"),
+        rx_errors: stat("rx_errors"),
+        tx_errors: stat("tx_errors"),
+        rx_dropped: stat("rx_dropped"),
+        tx_dropped: stat("tx_dropped"),
+        name,
+        ..NetworkInterface::default()
+    }
+}
@@ -0,0 +1,562 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::alerts::{Alert, AlertState, Severity};
+use crate::publicip::IpChange;
+use crate::config::{deserialize_duration, redacted_error};
+use crate::sampler::format_timestamp;
+
+/// Delivery attempts kept for `/notifications`, newest first.
+const DELIVERY_LOG_LIMIT: usize = 200;
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// Notifications waiting per destination before new ones are dropped.
+const QUEUE_LIMIT: usize = 100;
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Generic,
+    Slack,
+    Discord,
+    Ntfy,
+    Gotify,
+}
+
+fn default_retries() -> u32 {
+    5
+}
+
+fn default_backoff() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+/// A webhook destination loaded from `NOTIFIERS_FILE`.
+#[derive(Deserialize, Clone)]
+pub struct Destination {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub format: Format,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Events below this severity are not sent to this destination.
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+    #[serde(default = "default_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_backoff", deserialize_with = "deserialize_duration")]
+    pub initial_backoff: Duration,
+    #[serde(default = "default_timeout", deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+}
+
+/// Something worth telling a human about. Alerts are the main source, but
+/// anything that can describe itself this way can be delivered.
+#[derive(Serialize, Clone)]
+pub struct Notification {
+    pub kind: String,
+    pub title: String,
+    pub message: String,
+    pub severity: Severity,
+    pub at: String,
+    pub payload: Value,
+}
+
+impl Notification {
+    pub fn from_alert(alert: &Alert) -> Self {
+        let (verb, at) = match alert.state {
+            AlertState::Resolved => ("resolved", alert.resolved_at.clone()),
+            _ => ("firing", alert.fired_at.clone()),
+        };
+
+        let message = match alert.stale {
+            true => format!("{} stopped reporting (last value {})", alert.metric, alert.value),
+            false => format!(
+                "{} is {} (threshold {} {})",
+                alert.metric,
+                alert.value,
+                alert.op.as_str(),
+                alert.threshold
+            ),
+        };
+
+        Notification {
+            kind: format!("alert.{}", verb),
+            title: format!("[{}] {} {}", verb.to_uppercase(), alert.rule, alert.severity.as_str()),
+            message,
+            severity: alert.severity,
+            at: at.unwrap_or_else(|| format_timestamp(SystemTime::now())),
+            payload: serde_json::to_value(alert).unwrap_or(Value::Null),
+        }
+    }
+
+    pub fn from_ip_change(change: &IpChange) -> Self {
+        let family = if change.family == "ipv6" { "IPv6" } else { "IPv4" };
+        let (kind, severity, message) = match &change.previous {
+            Some(previous) => (
+                "public_ip.changed",
+                Severity::Warning,
+                format!("Public {} changed from {} to {}", family, previous, change.current),
+            ),
+            None => (
+                "public_ip.discovered",
+                Severity::Info,
+                format!("Public {} is {}", family, change.current),
+            ),
+        };
+
+        Notification {
+            kind: kind.to_string(),
+            title: format!("Public {} address {}", family, change.current),
+            message,
+            severity,
+            at: change.at.clone(),
+            payload: serde_json::to_value(change).unwrap_or(Value::Null),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Delivery {
+    pub destination: String,
+    pub kind: String,
+    pub title: String,
+    pub delivered: bool,
+    pub attempts: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub finished_at: String,
+}
+
+pub type SharedDeliveryLog = Arc<RwLock<VecDeque<Delivery>>>;
+
+/// A destination's worker and the queue feeding it.
+struct Queue {
+    name: String,
+    min_severity: Option<Severity>,
+    sender: SyncSender<Notification>,
+}
+
+/// Cloneable handle for queueing notifications. Each destination has one
+/// worker thread, so its notifications arrive in order (`resolved` never
+/// overtakes `firing`), and a bounded queue, so a slow endpoint costs at most
+/// `QUEUE_LIMIT` pending notifications rather than a thread each. Sending
+/// never blocks the sampler; a full queue drops the notification.
+#[derive(Clone)]
+pub struct Notifier {
+    queues: Arc<Vec<Queue>>,
+    log: SharedDeliveryLog,
+}
+
+impl Notifier {
+    pub fn spawn(destinations: Vec<Destination>, log: &SharedDeliveryLog) -> Self {
+        let queues = destinations
+            .into_iter()
+            .map(|destination| {
+                let (sender, receiver) = mpsc::sync_channel::<Notification>(QUEUE_LIMIT);
+                let queue = Queue {
+                    name: destination.name.clone(),
+                    min_severity: destination.min_severity,
+                    sender,
+                };
+                let log = Arc::clone(log);
+
+                thread::Builder::new()
+                    .name(format!("notify-{}", destination.name))
+                    .spawn(move || {
+                        for notification in receiver {
+                            let delivery = deliver(&destination, &notification);
+                            if !delivery.delivered {
+                                eprintln!(
+                                    "Failed to deliver {} to {}: {}",
+                                    delivery.kind,
+                                    delivery.destination,
+                                    delivery.error.as_deref().unwrap_or("unknown error")
+                                );
+                            }
+                            record(&log, delivery);
+                        }
+                    })
+                    .expect("failed to spawn notifier thread");
+                queue
+            })
+            .collect();
+
+        Notifier { queues: Arc::new(queues), log: Arc::clone(log) }
+    }
+
+    pub fn send(&self, notification: Notification) {
+        for queue in self.queues.iter() {
+            if queue.min_severity.is_some_and(|min| notification.severity < min) {
+                continue;
+            }
+
+            if let Err(TrySendError::Full(dropped)) = queue.sender.try_send(notification.clone()) {
+                eprintln!("Dropping {} for {}: delivery queue is full", dropped.kind, queue.name);
+                record(
+                    &self.log,
+                    Delivery {
+                        destination: queue.name.clone(),
+                        kind: dropped.kind,
+                        title: dropped.title,
+                        delivered: false,
+                        attempts: 0,
+                        status_code: None,
+                        error: Some("delivery queue is full".to_string()),
+                        finished_at: format_timestamp(SystemTime::now()),
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn record(log: &SharedDeliveryLog, delivery: Delivery) {
+    let mut log = log.write().unwrap();
+    log.push_front(delivery);
+    log.truncate(DELIVERY_LOG_LIMIT);
+}
+
+/// POSTs the notification, retrying with exponential backoff on transport
+/// errors, 5xx and 429. Other 4xx responses mean the destination is
+/// misconfigured, so they are not retried.
+fn deliver(destination: &Destination, notification: &Notification) -> Delivery {
+    let agent = ureq::AgentBuilder::new().timeout(destination.timeout).build();
+    let mut backoff = destination.initial_backoff;
+    let mut attempts = 0;
+    let mut status_code = None;
+    let mut error = None;
+
+    while attempts <= destination.max_retries {
+        if attempts > 0 {
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+        attempts += 1;
+
+        let mut request = agent.post(&destination.url);
+        for (name, value) in &destination.headers {
+            request = request.set(name, value);
+        }
+
+        let result = match render(destination.format, notification) {
+            Body::Json(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string()),
+            Body::Text(headers, body) => {
+                for (name, value) in headers {
+                    request = request.set(name, &value);
+                }
+                request.send_string(&body)
+            }
+        };
+
+        match result {
+            Ok(response) => {
+                status_code = Some(response.status());
+                error = None;
+                break;
+            }
+            Err(ureq::Error::Status(code, _)) => {
+                status_code = Some(code);
+                error = Some(format!("HTTP {}", code));
+                if code < 500 && code != 429 {
+                    break;
+                }
+            }
+            Err(e) => error = Some(redacted_error(&e)),
+        }
+    }
+
+    Delivery {
+        destination: destination.name.clone(),
+        kind: notification.kind.clone(),
+        title: notification.title.clone(),
+        delivered: error.is_none(),
+        attempts,
+        status_code,
+        error,
+        finished_at: format_timestamp(SystemTime::now()),
+    }
+}
+
+enum Body {
+    Json(Value),
+    Text(Vec<(&'static str, String)>, String),
+}
+
+fn render(format: Format, notification: &Notification) -> Body {
+    let text = format!("{}\n{}", notification.title, notification.message);
+
+    match format {
+        Format::Generic => Body::Json(serde_json::to_value(notification).unwrap_or(Value::Null)),
+        Format::Slack => Body::Json(json!({ "text": text })),
+        Format::Discord => Body::Json(json!({ "content": text })),
+        Format::Ntfy => Body::Text(
+            vec![
+                ("Title", notification.title.clone()),
+                ("Priority", ntfy_priority(notification.severity).to_string()),
+                ("Tags", notification.kind.clone()),
+            ],
+            notification.message.clone(),
+        ),
+        Format::Gotify => Body::Json(json!({
+            "title": notification.title,
+            "message": notification.message,
+            "priority": gotify_priority(notification.severity),
+        })),
+    }
+}
+
+fn ntfy_priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Info => 3,
+        Severity::Warning => 4,
+        Severity::Critical => 5,
+    }
+}
+
+fn gotify_priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Info => 2,
+        Severity::Warning => 5,
+        Severity::Critical => 8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    struct Request {
+        head: String,
+        body: String,
+    }
+
+    impl Request {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.head.lines().find_map(|line| {
+                let (key, value) = line.split_once(": ")?;
+                key.eq_ignore_ascii_case(name).then_some(value)
+            })
+        }
+
+        fn json(&self) -> Value {
+            serde_json::from_str(&self.body).unwrap()
+        }
+    }
+
+    /// Answers one request per status code and hands back what it received.
+    fn stand_in(statuses: &'static [u16]) -> (String, thread::JoinHandle<Vec<Request>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            statuses
+                .iter()
+                .map(|status| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut head = String::new();
+                    while reader.read_line(&mut head).unwrap() > 2 {}
+                    let mut request = Request { head, body: String::new() };
+                    let length: u64 = request.header("Content-Length").map_or(0, |l| l.parse().unwrap());
+                    reader.take(length).read_to_string(&mut request.body).unwrap();
+
+                    write!(stream, "HTTP/1.1 {} Stand-in\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status)
+                        .unwrap();
+                    request
+                })
+                .collect()
+        });
+        (url, handle)
+    }
+
+    fn destination(url: &str, format: Format) -> Destination {
+        Destination {
+            name: "test".to_string(),
+            url: url.to_string(),
+            format,
+            headers: HashMap::from([("X-Token".to_string(), "secret".to_string())]),
+            min_severity: None,
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            timeout: Duration::from_secs(2),
+        }
+    }
+
+    fn notification() -> Notification {
+        Notification {
+            kind: "alert.firing".to_string(),
+            title: "[FIRING] cpu_hot critical".to_string(),
+            message: "cpu_temp is 91 (threshold > 85)".to_string(),
+            severity: Severity::Critical,
+            at: "2026-01-01T00:00:00Z".to_string(),
+            payload: json!({ "rule": "cpu_hot" }),
+        }
+    }
+
+    fn send(format: Format) -> Request {
+        let (url, stand_in) = stand_in(&[200]);
+        let delivery = deliver(&destination(&url, format), &notification());
+        assert!(delivery.delivered);
+        assert_eq!((delivery.attempts, delivery.status_code), (1, Some(200)));
+
+        let request = stand_in.join().unwrap().pop().unwrap();
+        assert!(request.head.starts_with("POST /hook HTTP/1.1"));
+        assert_eq!(request.header("X-Token"), Some("secret"));
+        request
+    }
+
+    #[test]
+    fn renders_generic_json() {
+        let request = send(Format::Generic);
+        assert_eq!(request.header("Content-Type"), Some("application/json"));
+        let body = request.json();
+        assert_eq!(body["kind"], "alert.firing");
+        assert_eq!(body["severity"], "critical");
+        assert_eq!(body["payload"]["rule"], "cpu_hot");
+    }
+
+    #[test]
+    fn renders_chat_formats() {
+        let text = "[FIRING] cpu_hot critical\ncpu_temp is 91 (threshold > 85)";
+        assert_eq!(send(Format::Slack).json(), json!({ "text": text }));
+        assert_eq!(send(Format::Discord).json(), json!({ "content": text }));
+        assert_eq!(
+            send(Format::Gotify).json(),
+            json!({ "title": "[FIRING] cpu_hot critical", "message": "cpu_temp is 91 (threshold > 85)", "priority": 8 })
+        );
+    }
+
+    #[test]
+    fn renders_ntfy_headers_and_plain_body() {
+        let request = send(Format::Ntfy);
+        assert_eq!(request.header("Title"), Some("[FIRING] cpu_hot critical"));
+        assert_eq!(request.header("Priority"), Some("5"));
+        assert_eq!(request.header("Tags"), Some("alert.firing"));
+        assert_eq!(request.body, "cpu_temp is 91 (threshold > 85)");
+    }
+
+    #[test]
+    fn retries_server_errors_and_rate_limits() {
+        let (url, stand_in) = stand_in(&[503, 429, 200]);
+        let delivery = deliver(&destination(&url, Format::Generic), &notification());
+
+        assert!(delivery.delivered);
+        assert_eq!((delivery.attempts, delivery.status_code), (3, Some(200)));
+        assert_eq!(stand_in.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_max_retries() {
+        let (url, stand_in) = stand_in(&[500, 502, 503, 504]);
+        let delivery = deliver(&destination(&url, Format::Generic), &notification());
+
+        assert!(!delivery.delivered);
+        assert_eq!((delivery.attempts, delivery.status_code), (4, Some(504)));
+        assert_eq!(delivery.error.as_deref(), Some("HTTP 504"));
+        assert_eq!(stand_in.join().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        for status in [&[400], &[401], &[403], &[404]] {
+            let (url, stand_in) = stand_in(status);
+            let delivery = deliver(&destination(&url, Format::Slack), &notification());
+
+            assert!(!delivery.delivered);
+            assert_eq!((delivery.attempts, delivery.status_code), (1, Some(status[0])));
+            assert_eq!(stand_in.join().unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn errors_leave_webhook_secrets_out() {
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let mut unreachable = destination(
+            &format!("http://user:hunter2@{}/hooks/SECRET-PATH?token=SECRET-QUERY", closed),
+            Format::Gotify,
+        );
+        unreachable.max_retries = 0;
+        let (url, stand_in) = stand_in(&[500]);
+        let mut failing = destination(&format!("{}/SECRET-PATH?token=SECRET-QUERY", url), Format::Slack);
+        failing.max_retries = 0;
+
+        for destination in [unreachable, failing] {
+            let error = deliver(&destination, &notification()).error.unwrap();
+            assert!(!error.contains("SECRET") && !error.contains("hunter2"), "{}", error);
+        }
+        assert_eq!(stand_in.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn delivers_in_order_per_destination() {
+        let (url, stand_in) = stand_in(&[503, 200, 200, 200]);
+        let log = SharedDeliveryLog::default();
+        let notifier = Notifier::spawn(vec![destination(&url, Format::Generic)], &log);
+
+        for kind in ["alert.firing", "alert.resolved", "alert.firing"] {
+            notifier.send(Notification { kind: kind.to_string(), ..notification() });
+        }
+
+        let kinds: Vec<String> = stand_in.join().unwrap().iter().map(|r| r.json()["kind"].to_string()).collect();
+        assert_eq!(kinds, [r#""alert.firing""#, r#""alert.firing""#, r#""alert.resolved""#, r#""alert.firing""#]);
+    }
+
+    #[test]
+    fn drops_notifications_when_a_destination_falls_behind() {
+        // Accepts connections but never answers, so the worker stays busy.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stalled = destination(&format!("http://{}/hook", listener.local_addr().unwrap()), Format::Generic);
+        stalled.timeout = Duration::from_secs(30);
+        let log = SharedDeliveryLog::default();
+        let notifier = Notifier::spawn(vec![stalled], &log);
+
+        for _ in 0..QUEUE_LIMIT + 5 {
+            notifier.send(notification());
+        }
+
+        let log = log.read().unwrap();
+        assert!(log.len() >= 4 && log.len() <= 5, "{} dropped", log.len());
+        assert!(log.iter().all(|d| !d.delivered && d.attempts == 0));
+        assert_eq!(log[0].error.as_deref(), Some("delivery queue is full"));
+    }
+
+    #[test]
+    fn skips_destinations_above_the_severity() {
+        let (url, stand_in) = stand_in(&[200]);
+        let log = SharedDeliveryLog::default();
+        let mut critical_only = destination(&url, Format::Generic);
+        critical_only.min_severity = Some(Severity::Critical);
+        let notifier = Notifier::spawn(vec![critical_only], &log);
+
+        notifier.send(Notification { kind: "info".to_string(), severity: Severity::Info, ..notification() });
+        notifier.send(notification());
+
+        let requests = stand_in.join().unwrap();
+        assert_eq!(requests[0].json()["kind"], "alert.firing");
+    }
+
+    #[test]
+    fn retries_transport_errors() {
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let delivery = deliver(&destination(&format!("http://{}/hook", closed), Format::Generic), &notification());
+
+        assert!(!delivery.delivered);
+        assert_eq!((delivery.attempts, delivery.status_code), (4, None));
+        assert!(delivery.error.is_some());
+    }
+}
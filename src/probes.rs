@@ -0,0 +1,468 @@
+use std::env;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use serde::Serialize;
+use url::{Position, Url};
+
+use crate::config::duration_from_env;
+
+#[derive(Serialize, Clone)]
+pub struct ProbeResult {
+    pub target: String,
+    pub kind: &'static str,
+    pub sent: u32,
+    pub received: u32,
+    pub loss_percentage: f64,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    /// Standard deviation of the round-trip times, as `ping` reports it.
+    pub mdev_ms: Option<f64>,
+    /// Mean absolute difference between consecutive round-trip times.
+    pub jitter_ms: Option<f64>,
+    /// Phase breakdown, only for `http` probes.
+    pub http: Option<HttpTiming>,
+    pub error: Option<String>,
+}
+
+/// Durations of each phase of one HTTP(S) request. Each phase is measured on
+/// its own; `total_ms` covers everything from DNS lookup to the end of the body.
+#[derive(Serialize, Clone, Default)]
+pub struct HttpTiming {
+    pub status_code: Option<u16>,
+    pub dns_ms: Option<f64>,
+    pub connect_ms: Option<f64>,
+    pub tls_ms: Option<f64>,
+    /// From sending the request to receiving the first response byte.
+    pub ttfb_ms: Option<f64>,
+    pub total_ms: Option<f64>,
+}
+
+impl ProbeResult {
+    /// Summarises `rtts` (one entry per successful probe) out of `sent`.
+    pub fn from_rtts(target: &str, kind: &'static str, sent: u32, rtts: &[f64], error: Option<String>) -> Self {
+        let received = rtts.len() as u32;
+        let n = rtts.len() as f64;
+
+        let (min_ms, avg_ms, max_ms, mdev_ms, jitter_ms) = if rtts.is_empty() {
+            (None, None, None, None, None)
+        } else {
+            let avg = rtts.iter().sum::<f64>() / n;
+            let variance = rtts.iter().map(|r| (r - avg).powi(2)).sum::<f64>() / n;
+            let jitter = (rtts.len() > 1).then(|| {
+                rtts.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (n - 1.0)
+            });
+
+            (
+                rtts.iter().copied().reduce(f64::min),
+                Some(avg),
+                rtts.iter().copied().reduce(f64::max),
+                Some(variance.sqrt()),
+                jitter,
+            )
+        };
+
+        ProbeResult {
+            target: target.to_string(),
+            kind,
+            sent,
+            received,
+            loss_percentage: if sent > 0 { (sent - received.min(sent)) as f64 / sent as f64 * 100.0 } else { 0.0 },
+            min_ms,
+            avg_ms,
+            max_ms,
+            mdev_ms,
+            jitter_ms,
+            http: None,
+            error,
+        }
+    }
+}
+
+/// Probes every configured target once per round, in parallel, so one
+/// unreachable host doesn't delay the others.
+pub struct Prober {
+    icmp_targets: Vec<String>,
+    tcp_targets: Vec<String>,
+    http_targets: Vec<String>,
+    count: u32,
+    icmp_timeout: Duration,
+    timeout: Duration,
+}
+
+impl Prober {
+    pub fn from_env() -> Self {
+        let list = |key: &str, default: &str| -> Vec<String> {
+            env::var(key)
+                .unwrap_or_else(|_| default.to_string())
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect()
+        };
+
+        Prober {
+            icmp_targets: list("PING_TARGETS", "8.8.8.8"),
+            tcp_targets: list("TCP_PROBE_TARGETS", ""),
+            http_targets: list("HTTP_PROBE_TARGETS", ""),
+            count: env::var("PING_COUNT").ok().and_then(|c| c.parse().ok()).filter(|&c| c > 0).unwrap_or(5),
+            icmp_timeout: duration_from_env("PING_TIMEOUT", "2s"),
+            timeout: duration_from_env("PROBE_TIMEOUT", "5s"),
+        }
+    }
+
+    pub fn run(&self) -> Vec<ProbeResult> {
+        let jobs: Vec<(&'static str, &String)> = self.icmp_targets.iter().map(|t| ("icmp", t))
+            .chain(self.tcp_targets.iter().map(|t| ("tcp", t)))
+            .chain(self.http_targets.iter().map(|t| ("http", t)))
+            .collect();
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = jobs.iter()
+                .map(|&(kind, target)| scope.spawn(move || match kind {
+                    "icmp" => icmp_probe(target, self.count, self.icmp_timeout),
+                    "tcp" => tcp_probe(target, self.count, self.timeout),
+                    _ => http_probe(target, self.count, self.timeout),
+                }))
+                .collect();
+
+            handles.into_iter()
+                .zip(&jobs)
+                .map(|(handle, &(kind, target))| {
+                    handle.join().unwrap_or_else(|_| {
+                        ProbeResult::from_rtts(target, kind, self.count, &[], Some("probe panicked".to_string()))
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+/// Sends `count` echo requests with the system `ping` and collects the
+/// per-reply round-trip times from its output.
+fn icmp_probe(target: &str, count: u32, timeout: Duration) -> ProbeResult {
+    let timeout_secs = timeout.as_secs().max(1);
+    // Replies are spaced 200 ms apart; the deadline bounds the whole run.
+    let deadline = timeout_secs + (count as u64).div_ceil(5);
+
+    let output = Command::new("ping")
+        .arg("-n")
+        .args(["-c", &count.to_string()])
+        .args(["-i", "0.2"])
+        .args(["-W", &timeout_secs.to_string()])
+        .args(["-w", &deadline.to_string()])
+        .arg(target)
+        .output();
+
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => return ProbeResult::from_rtts(target, "icmp", count, &[], Some(format!("failed to run ping: {}", e))),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rtts: Vec<f64> = stdout.lines().filter_map(parse_rtt).collect();
+
+    let error = if rtts.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Some(if stderr.is_empty() { "no replies".to_string() } else { stderr })
+    } else {
+        None
+    };
+
+    ProbeResult::from_rtts(target, "icmp", count, &rtts, error)
+}
+
+/// Extracts the RTT from a reply line such as
+/// `64 bytes from 8.8.8.8: icmp_seq=1 ttl=117 time=12.3 ms`.
+fn parse_rtt(line: &str) -> Option<f64> {
+    let rest = line.split_once("time=").or_else(|| line.split_once("time<"))?.1;
+    rest.split([' ', 'm']).next()?.parse().ok()
+}
+
+/// Opens `count` TCP connections to `host:port` and times each handshake.
+/// Needs neither the `ping` binary nor raw-socket privileges.
+fn tcp_probe(target: &str, count: u32, timeout: Duration) -> ProbeResult {
+    let addr = match resolve(target) {
+        Ok((addr, _)) => addr,
+        Err(e) => return ProbeResult::from_rtts(target, "tcp", count, &[], Some(e)),
+    };
+
+    let mut rtts = Vec::new();
+    let mut error = None;
+    for _ in 0..count {
+        let start = Instant::now();
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(_) => rtts.push(millis_since(start)),
+            Err(e) => error = Some(e.to_string()),
+        }
+    }
+
+    ProbeResult::from_rtts(target, "tcp", count, &rtts, error.filter(|_| rtts.is_empty()))
+}
+
+/// Performs `count` sequential `GET`s against `url`, each on a fresh
+/// connection. The total times feed loss and jitter; the phase breakdown
+/// (DNS, TCP connect, TLS handshake, time to first byte) is the last one's.
+fn http_probe(url: &str, count: u32, timeout: Duration) -> ProbeResult {
+    let mut rtts = Vec::new();
+    let mut error = None;
+    let mut last = HttpTiming::default();
+    for _ in 0..count {
+        let mut timing = HttpTiming::default();
+        match timed_request(url, timeout, &mut timing) {
+            Ok(()) => rtts.extend(timing.total_ms),
+            Err(e) => error = Some(e),
+        }
+        last = timing;
+    }
+
+    let mut probe = ProbeResult::from_rtts(url, "http", count, &rtts, error.filter(|_| rtts.is_empty()));
+    probe.http = Some(last);
+    probe
+}
+
+fn timed_request(url: &str, timeout: Duration, timing: &mut HttpTiming) -> Result<(), String> {
+    let url = Url::parse(url).map_err(|e| format!("invalid URL: {}", e))?;
+    let host = url.host_str().ok_or("URL has no host")?.to_string();
+    let port = url.port_or_known_default().ok_or("URL has no port")?;
+    let https = match url.scheme() {
+        "https" => true,
+        "http" => false,
+        other => return Err(format!("unsupported scheme {}", other)),
+    };
+
+    let start = Instant::now();
+    let (addr, dns) = resolve(&format!("{}:{}", host, port))?;
+    timing.dns_ms = Some(dns);
+
+    let connect_start = Instant::now();
+    let socket = TcpStream::connect_timeout(&addr, timeout).map_err(|e| format!("connect failed: {}", e))?;
+    timing.connect_ms = Some(millis_since(connect_start));
+    socket.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    socket.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+
+    let mut stream: Box<dyn ReadWrite> = if https {
+        let tls_start = Instant::now();
+        let name = ServerName::try_from(host.trim_start_matches('[').trim_end_matches(']').to_string())
+            .map_err(|e| format!("invalid server name: {}", e))?;
+        let conn = ClientConnection::new(tls_config(), name).map_err(|e| e.to_string())?;
+        let mut tls = StreamOwned::new(conn, socket);
+        while tls.conn.is_handshaking() {
+            tls.conn.complete_io(&mut tls.sock).map_err(|e| format!("TLS handshake failed: {}", e))?;
+        }
+        timing.tls_ms = Some(millis_since(tls_start));
+        Box::new(tls)
+    } else {
+        Box::new(socket)
+    };
+
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: status_server\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+        path,
+        &url[Position::BeforeHost..Position::AfterPort]
+    );
+
+    let request_start = Instant::now();
+    stream.write_all(request.as_bytes()).map_err(|e| format!("failed to send request: {}", e))?;
+
+    let mut first = [0u8; 1];
+    stream.read_exact(&mut first).map_err(|e| format!("no response: {}", e))?;
+    timing.ttfb_ms = Some(millis_since(request_start));
+
+    let mut head = vec![first[0]];
+    let mut buf = [0u8; 16 * 1024];
+    let mut body_bytes = 0usize;
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                if head.len() < 64 {
+                    head.extend_from_slice(&buf[..n.min(64)]);
+                }
+                body_bytes += n;
+                if body_bytes > MAX_HTTP_PROBE_BYTES {
+                    break;
+                }
+            }
+            // Servers often drop TLS connections without close_notify.
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(format!("failed to read response: {}", e)),
+        }
+    }
+    timing.total_ms = Some(millis_since(start));
+
+    let status_line = String::from_utf8_lossy(&head);
+    let status_code = status_line.split_whitespace().nth(1).and_then(|c| c.parse::<u16>().ok());
+    timing.status_code = status_code;
+
+    match status_code {
+        Some(code) if code < 400 => Ok(()),
+        Some(code) => Err(format!("HTTP {}", code)),
+        None => Err("malformed HTTP response".to_string()),
+    }
+}
+
+/// Responses are read to completion to time the full transfer, but probes
+/// shouldn't download arbitrarily large bodies.
+const MAX_HTTP_PROBE_BYTES: usize = 8 * 1024 * 1024;
+
+trait ReadWrite: Read + Write {}
+impl<T: Read + Write> ReadWrite for T {}
+
+fn tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+
+    CONFIG.get_or_init(|| {
+        let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+        let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Arc::new(config)
+    }).clone()
+}
+
+/// Resolves `host:port`, returning the first address and the lookup time.
+fn resolve(target: &str) -> Result<(SocketAddr, f64), String> {
+    let start = Instant::now();
+    let addr = target.to_socket_addrs()
+        .map_err(|e| format!("failed to resolve {}: {}", target, e))?
+        .next()
+        .ok_or_else(|| format!("{} resolved to no addresses", target))?;
+    Ok((addr, millis_since(start)))
+}
+
+fn millis_since(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn parses_linux_ping_replies() {
+        let output = "\
+PING 8.8.8.8 (8.8.8.8) 56(84) bytes of data.
+64 bytes from 8.8.8.8: icmp_seq=1 ttl=117 time=12.3 ms
+64 bytes from 8.8.8.8: icmp_seq=2 ttl=117 time=9.87 ms
+64 bytes from 127.0.0.1: icmp_seq=3 ttl=64 time<1 ms
+From 192.168.1.1 icmp_seq=4 Destination Host Unreachable
+
+--- 8.8.8.8 ping statistics ---
+4 packets transmitted, 3 received, +1 errors, 25% packet loss, time 3004ms
+rtt min/avg/max/mdev = 0.045/7.405/12.300/5.221 ms";
+
+        let rtts: Vec<f64> = output.lines().filter_map(parse_rtt).collect();
+        assert_eq!(rtts, [12.3, 9.87, 1.0]);
+    }
+
+    #[test]
+    fn parses_busybox_ping_replies() {
+        let output = "\
+PING 1.1.1.1 (1.1.1.1): 56 data bytes
+64 bytes from 1.1.1.1: seq=0 ttl=57 time=14.211 ms
+64 bytes from 1.1.1.1: seq=2 ttl=57 time=15.004 ms
+
+--- 1.1.1.1 ping statistics ---
+3 packets transmitted, 2 packets received, 33% packet loss
+round-trip min/avg/max = 14.211/14.607/15.004 ms";
+
+        let rtts: Vec<f64> = output.lines().filter_map(parse_rtt).collect();
+        assert_eq!(rtts, [14.211, 15.004]);
+    }
+
+    #[test]
+    fn timeouts_produce_no_rtt() {
+        for line in [
+            "no answer yet for icmp_seq=2",
+            "Request timeout for icmp_seq 0",
+            "5 packets transmitted, 0 received, 100% packet loss, time 4086ms",
+            "64 bytes from 8.8.8.8: icmp_seq=1 ttl=117 time=garbage ms",
+        ] {
+            assert_eq!(parse_rtt(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn summarises_loss_and_jitter() {
+        let probe = ProbeResult::from_rtts("8.8.8.8", "icmp", 5, &[10.0, 14.0, 12.0, 16.0], None);
+
+        assert_eq!((probe.sent, probe.received), (5, 4));
+        assert_eq!(probe.loss_percentage, 20.0);
+        assert_eq!((probe.min_ms, probe.avg_ms, probe.max_ms), (Some(10.0), Some(13.0), Some(16.0)));
+        assert_eq!(probe.mdev_ms, Some(5.0f64.sqrt()));
+        // |14-10| + |12-14| + |16-12| over three gaps.
+        assert_eq!(probe.jitter_ms, Some(10.0 / 3.0));
+    }
+
+    #[test]
+    fn single_or_missing_replies_have_no_jitter() {
+        let one = ProbeResult::from_rtts("8.8.8.8", "icmp", 3, &[10.0], None);
+        assert_eq!((one.mdev_ms, one.jitter_ms), (Some(0.0), None));
+
+        let none = ProbeResult::from_rtts("8.8.8.8", "icmp", 3, &[], Some("no replies".to_string()));
+        assert_eq!(none.loss_percentage, 100.0);
+        assert_eq!((none.min_ms, none.avg_ms, none.jitter_ms), (None, None, None));
+
+        assert_eq!(ProbeResult::from_rtts("8.8.8.8", "icmp", 0, &[], None).loss_percentage, 0.0);
+    }
+
+    /// Answers one request per connection with the next of `statuses`.
+    fn stand_in(statuses: &'static [u16]) -> (String, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/generate_204", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            for &status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let _ = write!(stream, "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            }
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn http_probe_samples_every_round() {
+        let (url, stand_in) = stand_in(&[204, 204, 204, 204]);
+        let probe = http_probe(&url, 4, Duration::from_secs(2));
+        stand_in.join().unwrap();
+
+        assert_eq!((probe.sent, probe.received, probe.loss_percentage), (4, 4, 0.0));
+        assert!(probe.jitter_ms.is_some());
+        assert!(probe.error.is_none());
+        let timing = probe.http.unwrap();
+        assert_eq!(timing.status_code, Some(204));
+        assert!(timing.connect_ms.is_some() && timing.tls_ms.is_none());
+    }
+
+    #[test]
+    fn http_probe_counts_error_statuses_as_lost() {
+        let (url, flaky) = stand_in(&[204, 503, 204, 204]);
+        let probe = http_probe(&url, 4, Duration::from_secs(2));
+        flaky.join().unwrap();
+        assert_eq!((probe.received, probe.loss_percentage), (3, 25.0));
+        assert!(probe.error.is_none());
+
+        let (url, failing) = stand_in(&[500, 500]);
+        let probe = http_probe(&url, 2, Duration::from_secs(2));
+        failing.join().unwrap();
+        assert_eq!(probe.loss_percentage, 100.0);
+        assert_eq!(probe.error.as_deref(), Some("HTTP 500"));
+    }
+}
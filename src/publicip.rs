@@ -0,0 +1,265 @@
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::duration_from_env;
+use crate::ddns::Ddns;
+use crate::notify::{Notification, Notifier};
+use crate::sampler::format_timestamp;
+
+/// Change events kept for `/public-ip/events`, newest first.
+const EVENT_LIMIT: usize = 500;
+const EVENTS_FILE: &str = "public-ip-events.json";
+
+#[derive(Serialize, Clone, Default)]
+pub struct PublicIp {
+    pub ipv4: Option<String>,
+    pub ipv6: Option<String>,
+}
+
+impl PublicIp {
+    /// The address reported as `public_ip`, preferring IPv4.
+    pub fn primary(&self) -> Option<String> {
+        self.ipv4.clone().or_else(|| self.ipv6.clone())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    fn as_str(self) -> &'static str {
+        match self {
+            Family::V4 => "IPv4",
+            Family::V6 => "IPv6",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Family::V4 => "ipv4",
+            Family::V6 => "ipv6",
+        }
+    }
+
+    fn matches(self, addr: &IpAddr) -> bool {
+        match self {
+            Family::V4 => addr.is_ipv4(),
+            Family::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+/// One address family's providers together with the last address they
+/// returned, which is kept for `cache_ttl` while every provider fails.
+struct Lookup {
+    family: Family,
+    providers: Vec<String>,
+    cached: Option<(String, Instant)>,
+}
+
+/// Discovers the public IPv4 and IPv6 addresses by asking plain-text "what
+/// is my IP" services, trying each configured provider in order.
+pub struct PublicIpResolver {
+    lookups: Vec<Lookup>,
+    timeout: Duration,
+    cache_ttl: Duration,
+}
+
+impl PublicIpResolver {
+    /// Returns `None` when `PUBLIC_IP_LOOKUP=false`, for hosts that must not
+    /// make outbound requests.
+    pub fn from_env() -> Option<Self> {
+        if env::var("PUBLIC_IP_LOOKUP").is_ok_and(|v| v == "false") {
+            return None;
+        }
+
+        let list = |key: &str, default: &str| -> Vec<String> {
+            env::var(key)
+                .unwrap_or_else(|_| default.to_string())
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect()
+        };
+
+        let lookups = [
+            (Family::V4, list("PUBLIC_IP_V4_PROVIDERS", "https://api.ipify.org,https://ipv4.icanhazip.com")),
+            (Family::V6, list("PUBLIC_IP_V6_PROVIDERS", "https://api6.ipify.org,https://ipv6.icanhazip.com")),
+        ];
+
+        Some(PublicIpResolver {
+            lookups: lookups
+                .into_iter()
+                .filter(|(_, providers)| !providers.is_empty())
+                .map(|(family, providers)| Lookup { family, providers, cached: None })
+                .collect(),
+            timeout: duration_from_env("PUBLIC_IP_TIMEOUT", "5s"),
+            cache_ttl: duration_from_env("PUBLIC_IP_CACHE_TTL", "1h"),
+        })
+    }
+
+    pub fn lookup(&mut self) -> PublicIp {
+        let mut ip = PublicIp::default();
+
+        for lookup in &mut self.lookups {
+            let found = match discover(lookup.family, &lookup.providers, self.timeout) {
+                Ok(addr) => {
+                    lookup.cached = Some((addr.clone(), Instant::now()));
+                    Some(addr)
+                }
+                Err(e) => {
+                    eprintln!("Public {} lookup failed: {}", lookup.family.as_str(), e);
+                    lookup
+                        .cached
+                        .as_ref()
+                        .filter(|(_, at)| at.elapsed() < self.cache_ttl)
+                        .map(|(addr, _)| addr.clone())
+                }
+            };
+
+            match lookup.family {
+                Family::V4 => ip.ipv4 = found,
+                Family::V6 => ip.ipv6 = found,
+            }
+        }
+
+        ip
+    }
+}
+
+/// Asks each provider in turn over a connection restricted to `family`, so an
+/// IPv6 lookup can't be answered over IPv4 and vice versa.
+fn discover(family: Family, providers: &[String], timeout: Duration) -> Result<String, String> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .resolver(move |addr: &str| -> io::Result<Vec<SocketAddr>> {
+            Ok(addr.to_socket_addrs()?.filter(|a| family.matches(&a.ip())).collect())
+        })
+        .build();
+
+    let mut errors = Vec::new();
+    for provider in providers {
+        let response = agent
+            .get(provider)
+            .call()
+            .map_err(|e| e.to_string())
+            .and_then(|res| res.into_string().map_err(|e| e.to_string()));
+
+        match response {
+            Ok(body) => match body.trim().parse::<IpAddr>() {
+                Ok(addr) if family.matches(&addr) => return Ok(addr.to_string()),
+                Ok(addr) => errors.push(format!("{}: returned {} address {}", provider, family_of(&addr), addr)),
+                Err(_) => errors.push(format!("{}: not an IP address", provider)),
+            },
+            Err(e) => errors.push(format!("{}: {}", provider, e)),
+        }
+    }
+
+    Err(errors.join("; "))
+}
+
+fn family_of(addr: &IpAddr) -> &'static str {
+    if addr.is_ipv4() { Family::V4.as_str() } else { Family::V6.as_str() }
+}
+
+/// A public address seen for the first time or replacing a different one.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IpChange {
+    /// `ipv4` or `ipv6`.
+    pub family: String,
+    /// `None` when nothing was known before, e.g. on a fresh install.
+    pub previous: Option<String>,
+    pub current: String,
+    pub at: String,
+}
+
+pub type SharedIpEvents = Arc<RwLock<VecDeque<IpChange>>>;
+
+/// Compares each lookup with the last known addresses, records changes and
+/// notifies about them, then hands the addresses to the DNS updaters. A
+/// failed lookup is not treated as a change. With a data directory the log
+/// is saved there, so changes that happen while the server is down are
+/// still noticed on the next start.
+pub struct IpMonitor {
+    events: SharedIpEvents,
+    path: Option<PathBuf>,
+    notifier: Notifier,
+    ddns: Ddns,
+}
+
+impl IpMonitor {
+    pub fn new(data_dir: Option<&Path>, notifier: &Notifier, ddns: Ddns) -> Self {
+        let path = data_dir.map(|dir| dir.join(EVENTS_FILE));
+        let events: VecDeque<IpChange> = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| {
+                serde_json::from_str(&json)
+                    .map_err(|e| eprintln!("Ignoring unreadable {}: {}", EVENTS_FILE, e))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        IpMonitor { events: Arc::new(RwLock::new(events)), path, notifier: notifier.clone(), ddns }
+    }
+
+    pub fn events(&self) -> SharedIpEvents {
+        Arc::clone(&self.events)
+    }
+
+    pub fn observe(&self, ip: &PublicIp) -> Vec<IpChange> {
+        let mut events = self.events.write().unwrap();
+        let mut changes = Vec::new();
+
+        for (family, current) in [(Family::V4, &ip.ipv4), (Family::V6, &ip.ipv6)] {
+            let Some(current) = current else { continue };
+            let previous = events.iter().find(|e| e.family == family.key()).map(|e| e.current.clone());
+            if previous.as_ref() == Some(current) {
+                continue;
+            }
+
+            let change = IpChange {
+                family: family.key().to_string(),
+                previous,
+                current: current.clone(),
+                at: format_timestamp(SystemTime::now()),
+            };
+            println!(
+                "🌐 Public {} {} -> {}",
+                family.as_str(),
+                change.previous.as_deref().unwrap_or("unknown"),
+                change.current
+            );
+            self.notifier.send(Notification::from_ip_change(&change));
+            events.push_front(change.clone());
+            changes.push(change);
+        }
+
+        if !changes.is_empty() {
+            events.truncate(EVENT_LIMIT);
+            if let Some(path) = &self.path {
+                let saved = serde_json::to_string_pretty(&*events)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+                if let Err(e) = saved {
+                    eprintln!("Failed to save {}: {}", path.display(), e);
+                }
+            }
+        }
+        drop(events);
+
+        self.ddns.update(ip);
+        changes
+    }
+}
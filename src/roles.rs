@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::env;
+
+use serde::Deserialize;
+
+use crate::config::{self, wildcard_match};
+use crate::hwmon::TempReading;
+
+/// Matches temperature readings by chip and, optionally, label or channel.
+/// Patterns are case-insensitive and support `*` and `?` wildcards.
+#[derive(Deserialize, Clone)]
+pub struct SensorPattern {
+    pub chip: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+}
+
+impl SensorPattern {
+    fn new(chip: &str, label: Option<&str>) -> Self {
+        SensorPattern { chip: chip.to_string(), label: label.map(str::to_string), channel: None }
+    }
+
+    fn matches(&self, reading: &TempReading) -> bool {
+        wildcard_match(&self.chip, &reading.chip)
+            && self.label.as_deref().is_none_or(|p| reading.label.as_deref().is_some_and(|l| wildcard_match(p, l)))
+            && self.channel.as_deref().is_none_or(|p| wildcard_match(p, &reading.channel))
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Every built-in profile in turn, so most hosts work unconfigured.
+    #[default]
+    Auto,
+    Amd,
+    Intel,
+    Arm,
+}
+
+impl Profile {
+    fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "auto" => Some(Profile::Auto),
+            "amd" => Some(Profile::Amd),
+            "intel" => Some(Profile::Intel),
+            "arm" => Some(Profile::Arm),
+            _ => None,
+        }
+    }
+
+    /// Built-in patterns for `motherboard_temp`, `cpu_temp` and `gpu_temp`,
+    /// tried in order.
+    fn roles(self) -> [(&'static str, Vec<SensorPattern>); 3] {
+        let p = SensorPattern::new;
+
+        match self {
+            Profile::Amd => [
+                ("motherboard_temp", vec![
+                    SensorPattern { channel: Some("temp1".to_string()), ..p("*asus*", None) },
+                    p("acpitz", None),
+                    p("nct*", Some("SYSTIN")),
+                    p("it87*", None),
+                ]),
+                ("cpu_temp", vec![p("k10temp", Some("Tctl")), p("k10temp", Some("Tdie")), p("k10temp", None), p("zenpower", None)]),
+                ("gpu_temp", vec![p("amdgpu", Some("edge")), p("amdgpu", None)]),
+            ],
+            Profile::Intel => [
+                ("motherboard_temp", vec![p("acpitz", None), p("pch_*", None), p("nct*", Some("SYSTIN"))]),
+                ("cpu_temp", vec![p("coretemp", Some("Package id 0")), p("x86_pkg_temp", None), p("coretemp", None)]),
+                ("gpu_temp", vec![p("nouveau", None), p("i915", None), p("xe", None)]),
+            ],
+            Profile::Arm => [
+                ("motherboard_temp", vec![p("soc_thermal", None), p("soc-thermal", None)]),
+                ("cpu_temp", vec![p("cpu_thermal", None), p("cpu-thermal", None), p("cpu*_thermal", None), p("cpu*-thermal", None)]),
+                ("gpu_temp", vec![p("gpu_thermal", None), p("gpu-thermal", None), p("gpu*_thermal", None)]),
+            ],
+            Profile::Auto => {
+                let [amd, intel, arm] = [Profile::Amd, Profile::Intel, Profile::Arm].map(Profile::roles);
+                let mut merged = amd;
+                for (role, patterns) in merged.iter_mut() {
+                    for other in intel.iter().chain(arm.iter()).filter(|(name, _)| name == role) {
+                        patterns.extend(other.1.iter().cloned());
+                    }
+                }
+                merged
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct RoleConfig {
+    #[serde(default)]
+    profile: Option<Profile>,
+    #[serde(default)]
+    roles: BTreeMap<String, Vec<SensorPattern>>,
+}
+
+struct Role {
+    name: String,
+    patterns: Vec<SensorPattern>,
+}
+
+/// Decides which temperature reading counts as `cpu_temp`, `gpu_temp`, etc.
+/// Starts from a built-in profile; `TEMP_ROLES_FILE` can override those roles
+/// or add new ones such as `nvme_temp`.
+pub struct RoleMap {
+    roles: Vec<Role>,
+}
+
+impl RoleMap {
+    pub fn from_env() -> Self {
+        let file: RoleConfig = config::json_file_from_env("TEMP_ROLES_FILE").unwrap_or_default();
+        let profile = env::var("TEMP_PROFILE").ok()
+            .and_then(|p| Profile::parse(&p).or_else(|| {
+                eprintln!("Unknown TEMP_PROFILE {:?}, expected auto, amd, intel or arm", p);
+                None
+            }))
+            .or(file.profile)
+            .unwrap_or_default();
+
+        RoleMap::new(profile, file.roles)
+    }
+
+    /// The profile's built-in roles, with `overrides` replacing roles of the
+    /// same name and adding the rest.
+    fn new(profile: Profile, overrides: BTreeMap<String, Vec<SensorPattern>>) -> Self {
+        let mut roles: Vec<Role> = profile.roles()
+            .into_iter()
+            .map(|(name, patterns)| Role { name: name.to_string(), patterns })
+            .collect();
+
+        for (name, patterns) in overrides {
+            match roles.iter_mut().find(|r| r.name == name) {
+                Some(role) => role.patterns = patterns,
+                None => roles.push(Role { name, patterns }),
+            }
+        }
+
+        RoleMap { roles }
+    }
+
+    /// Value of every role, using the first pattern that matches any reading.
+    pub fn resolve(&self, readings: &[TempReading]) -> Vec<(&str, Option<f32>)> {
+        self.roles.iter()
+            .map(|role| {
+                let value = role.patterns.iter()
+                    .find_map(|pattern| readings.iter().find(|r| pattern.matches(r)))
+                    .map(|r| r.celsius);
+                (role.name.as_str(), value)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(chip: &str, channel: &str, label: Option<&str>, celsius: f32) -> TempReading {
+        TempReading {
+            device: format!("hwmon-{}", chip),
+            chip: chip.to_string(),
+            channel: channel.to_string(),
+            label: label.map(str::to_string),
+            celsius,
+            max: None,
+            critical: None,
+        }
+    }
+
+    fn resolved(map: &RoleMap, readings: &[TempReading]) -> Vec<(String, Option<f32>)> {
+        map.resolve(readings).into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+    }
+
+    fn role(map: &RoleMap, readings: &[TempReading], name: &str) -> Option<f32> {
+        map.resolve(readings).into_iter().find(|(n, _)| *n == name).and_then(|(_, value)| value)
+    }
+
+    #[test]
+    fn auto_profile_resolves_an_amd_desktop() {
+        let readings = [
+            reading("nvme", "temp1", Some("Composite"), 38.0),
+            reading("k10temp", "temp3", Some("Tccd1"), 61.0),
+            reading("k10temp", "temp1", Some("Tctl"), 64.5),
+            reading("amdgpu", "temp1", Some("edge"), 47.0),
+            reading("nct6799", "temp1", Some("SYSTIN"), 33.0),
+        ];
+
+        assert_eq!(
+            resolved(&RoleMap::new(Profile::Auto, BTreeMap::new()), &readings),
+            [
+                ("motherboard_temp".to_string(), Some(33.0)),
+                ("cpu_temp".to_string(), Some(64.5)),
+                ("gpu_temp".to_string(), Some(47.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn earlier_patterns_win_over_earlier_readings() {
+        let readings = [
+            reading("x86_pkg_temp", "temp", Some("x86_pkg_temp"), 70.0),
+            reading("coretemp", "temp2", Some("Core 0"), 55.0),
+            reading("coretemp", "temp1", Some("Package id 0"), 58.0),
+        ];
+        let intel = RoleMap::new(Profile::Intel, BTreeMap::new());
+
+        assert_eq!(role(&intel, &readings, "cpu_temp"), Some(58.0));
+        assert_eq!(role(&intel, &readings[..2], "cpu_temp"), Some(70.0));
+        assert_eq!(role(&intel, &readings[1..2], "cpu_temp"), Some(55.0), "falls back to any coretemp channel");
+    }
+
+    #[test]
+    fn profiles_only_use_their_own_patterns() {
+        let readings = [reading("k10temp", "temp1", Some("Tctl"), 64.5)];
+        assert_eq!(role(&RoleMap::new(Profile::Intel, BTreeMap::new()), &readings, "cpu_temp"), None);
+        assert_eq!(role(&RoleMap::new(Profile::Arm, BTreeMap::new()), &readings, "cpu_temp"), None);
+    }
+
+    #[test]
+    fn roles_file_overrides_and_adds_roles() {
+        let file: RoleConfig = serde_json::from_str(
+            r#"{
+                "profile": "arm",
+                "roles": {
+                    "cpu_temp": [{ "chip": "CPU?THERMAL", "label": "*" }],
+                    "nvme_temp": [{ "chip": "nvme", "label": "Sensor ?" }, { "chip": "nvme", "channel": "temp1" }]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(file.profile, Some(Profile::Arm)));
+        let map = RoleMap::new(file.profile.unwrap(), file.roles);
+
+        let readings = [
+            reading("cpu_thermal", "temp", None, 49.0),
+            reading("cpu-thermal", "temp", Some("cpu-thermal"), 51.0),
+            reading("nvme", "temp1", Some("Composite"), 36.0),
+            reading("nvme", "temp3", Some("Sensor 2"), 41.0),
+        ];
+
+        let names: Vec<String> = resolved(&map, &readings).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["motherboard_temp", "cpu_temp", "gpu_temp", "nvme_temp"]);
+        assert_eq!(role(&map, &readings, "cpu_temp"), Some(51.0), "a label pattern needs a label");
+        assert_eq!(role(&map, &readings, "nvme_temp"), Some(41.0));
+        assert_eq!(role(&map, &readings[..3], "nvme_temp"), Some(36.0));
+        assert_eq!(role(&map, &readings, "gpu_temp"), None);
+    }
+
+    #[test]
+    fn parses_profile_names_case_insensitively() {
+        assert!(matches!(Profile::parse("AMD"), Some(Profile::Amd)));
+        assert!(matches!(Profile::parse("auto"), Some(Profile::Auto)));
+        assert!(Profile::parse("sparc").is_none());
+    }
+}
@@ -0,0 +1,337 @@
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use sysinfo::{System, MINIMUM_CPU_UPDATE_INTERVAL};
+
+use crate::alerts::SharedAlerts;
+use crate::auth::Section;
+use crate::collectors::{self, UsageCollector, UsageSample};
+use crate::config::duration_from_env;
+use crate::diskio::{DiskIo, DiskIoCollector};
+use crate::disks::{DiskCollector, Filesystem};
+use crate::history::SharedHistory;
+use crate::hwmon::Sysfs;
+use crate::notify::{Notification, Notifier};
+use crate::models::{
+    DiskData, DiskTimestamps, NetworkData, NetworkTimestamps, SensorSample, ServerData, StatusResponse, TempData,
+    UsageData,
+};
+use crate::netif::{InterfaceCollector, NetworkInterface};
+use crate::probes::{ProbeResult, Prober};
+use crate::publicip::{IpMonitor, PublicIp, PublicIpResolver};
+use crate::roles::RoleMap;
+use crate::speedtest::SpeedResult;
+
+#[derive(Clone)]
+pub struct Sampled<T> {
+    pub value: T,
+    pub sampled_at: SystemTime,
+}
+
+impl<T> Sampled<T> {
+    pub fn now(value: T) -> Self {
+        Sampled { value, sampled_at: SystemTime::now() }
+    }
+
+    pub fn timestamp(&self) -> String {
+        format_timestamp(self.sampled_at)
+    }
+}
+
+pub fn format_timestamp(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+/// Latest value of every collected section, written by the sampler threads
+/// and read by the request handlers.
+#[derive(Default)]
+pub struct Snapshot {
+    pub usage: Option<Sampled<UsageSample>>,
+    pub sensors: Option<Sampled<SensorSample>>,
+    pub public_ip: Option<Sampled<PublicIp>>,
+    pub probes: Option<Sampled<Vec<ProbeResult>>>,
+    pub speedtest: Option<Sampled<SpeedResult>>,
+    pub interfaces: Option<Sampled<Vec<NetworkInterface>>>,
+    pub disks: Option<Sampled<Vec<Filesystem>>>,
+    pub disk_io: Option<Sampled<Vec<DiskIo>>>,
+}
+
+pub type SharedSnapshot = Arc<RwLock<Snapshot>>;
+
+pub struct Intervals {
+    pub usage: Duration,
+    pub temps: Duration,
+    pub public_ip: Duration,
+    pub probes: Duration,
+    pub speedtest: Duration,
+    pub interfaces: Duration,
+    pub disks: Duration,
+    pub disk_io: Duration,
+}
+
+impl Intervals {
+    pub fn from_env() -> Self {
+        Intervals {
+            usage: duration_from_env("USAGE_INTERVAL", "1s").max(MINIMUM_CPU_UPDATE_INTERVAL),
+            temps: duration_from_env("TEMPS_INTERVAL", "10s"),
+            public_ip: duration_from_env("PUBLIC_IP_INTERVAL", "10m"),
+            probes: duration_from_env("PING_INTERVAL", "30s"),
+            speedtest: duration_from_env("SPEEDTEST_INTERVAL", "1h"),
+            interfaces: duration_from_env("INTERFACES_INTERVAL", "10s"),
+            disks: duration_from_env("DISKS_INTERVAL", "30s"),
+            disk_io: duration_from_env("DISKIO_INTERVAL", "5s"),
+        }
+    }
+}
+
+/// Metric name and value pairs recorded to history and checked by alerts.
+pub type Points = Vec<(String, f64)>;
+
+/// Starts one background thread per section. Each thread samples immediately
+/// and then again every interval, so slow collectors (public IP, probes)
+/// never hold up the fast ones. Numeric fields are also appended to `history`
+/// and evaluated against the alert rules, whose state changes are notified.
+/// Public IP lookups are passed to `ip_monitor` to detect address changes.
+pub fn spawn(
+    intervals: &Intervals,
+    history: &SharedHistory,
+    alerts: &SharedAlerts,
+    notifier: &Notifier,
+    ip_monitor: IpMonitor,
+) -> Sampler {
+    let sampler = Sampler {
+        snapshot: SharedSnapshot::default(),
+        history: Arc::clone(history),
+        alerts: Arc::clone(alerts),
+        notifier: notifier.clone(),
+    };
+
+    let mut usage = UsageCollector::new();
+    sampler.spawn_loop("usage", intervals.usage, move || usage.sample(), usage_points, &[Section::Usage], |s, v| s.usage = Some(v));
+    let sysfs = Sysfs::from_env();
+    let roles = RoleMap::from_env();
+    let collect_sensors = move || {
+        let chips = sysfs.chips();
+        let temps = collectors::get_all_temps(&chips, &roles);
+        SensorSample { chips, temps }
+    };
+    sampler.spawn_loop("sensors", intervals.temps, collect_sensors, temp_points, Section::TEMPERATURES, |s, v| s.sensors = Some(v));
+    if let Some(mut resolver) = PublicIpResolver::from_env() {
+        let lookup = move || {
+            let ip = resolver.lookup();
+            ip_monitor.observe(&ip);
+            ip
+        };
+        sampler.spawn_loop("public-ip", intervals.public_ip, lookup, |_| Vec::new(), &[Section::PublicIp], |s, v| s.public_ip = Some(v));
+    }
+    let prober = Prober::from_env();
+    sampler.spawn_loop("probes", intervals.probes, move || prober.run(), |p| probe_points(p), &[Section::Probes], |s, v| s.probes = Some(v));
+    let mut interfaces = InterfaceCollector::from_env();
+    sampler.spawn_loop("interfaces", intervals.interfaces, move || interfaces.sample(), |i| interface_points(i), &[Section::Interfaces], |s, v| s.interfaces = Some(v));
+
+    let disks = DiskCollector::from_env();
+    sampler.spawn_loop("disks", intervals.disks, move || disks.filesystems(), |fs| disk_points(fs), &[Section::Disks], |s, v| s.disks = Some(v));
+
+    let mut disk_io = DiskIoCollector::from_env();
+    sampler.spawn_loop("disk-io", intervals.disk_io, move || disk_io.sample(), |io| disk_io_points(io), &[Section::Disks], |s, v| s.disk_io = Some(v));
+
+    sampler
+}
+
+/// Handle for publishing samples: stores them in the snapshot, records their
+/// points to history and runs them through the alert rules.
+#[derive(Clone)]
+pub struct Sampler {
+    pub snapshot: SharedSnapshot,
+    history: SharedHistory,
+    alerts: SharedAlerts,
+    notifier: Notifier,
+}
+
+impl Sampler {
+    /// `sections` are the status sections the loop's points expose, so
+    /// `/history` and `/alerts` can hide them from tokens denied those sections.
+    fn spawn_loop<T, C, S>(
+        &self,
+        name: &str,
+        interval: Duration,
+        mut collect: C,
+        points: fn(&T) -> Points,
+        sections: &'static [Section],
+        store: S,
+    ) where
+        T: Send + 'static,
+        C: FnMut() -> T + Send + 'static,
+        S: Fn(&mut Snapshot, Sampled<T>) + Send + 'static,
+    {
+        let sampler = self.clone();
+
+        thread::Builder::new()
+            .name(format!("sampler-{}", name))
+            .spawn(move || loop {
+                let sample = Sampled::now(collect());
+                let points = points(&sample.value);
+                sampler.publish(sample, &points, sections, &store);
+                thread::sleep(interval);
+            })
+            .expect("failed to spawn sampler thread");
+    }
+
+    pub fn publish<T>(
+        &self,
+        sample: Sampled<T>,
+        points: &[(String, f64)],
+        sections: &'static [Section],
+        store: impl FnOnce(&mut Snapshot, Sampled<T>),
+    ) {
+        self.history.record(sample.sampled_at, points, sections);
+
+        for alert in self.alerts.write().unwrap().observe(sample.sampled_at, points) {
+            println!(
+                "🔔 Alert {} {}: {} = {} (threshold {})",
+                alert.rule,
+                alert.state.as_str(),
+                alert.metric,
+                alert.value,
+                alert.threshold
+            );
+            self.notifier.send(Notification::from_alert(&alert));
+        }
+
+        store(&mut self.snapshot.write().unwrap(), sample);
+    }
+}
+
+fn usage_points(usage: &UsageSample) -> Points {
+    vec![
+        ("cpu_percentage".to_string(), usage.cpu_percentage as f64),
+        ("memory".to_string(), usage.memory_gib() as f64),
+        ("memory_percentage".to_string(), usage.memory_percentage() as f64),
+    ]
+}
+
+fn temp_points(sample: &SensorSample) -> Points {
+    let temps = &sample.temps;
+    [
+        ("motherboard_temp", temps.motherboard_temp),
+        ("cpu_temp", temps.cpu_temp),
+        ("gpu_temp", temps.gpu_temp),
+    ]
+    .into_iter()
+    .chain(temps.extra.iter().map(|(role, &value)| (role.as_str(), value)))
+    .filter_map(|(metric, value)| Some((metric.to_string(), value? as f64)))
+    .collect()
+}
+
+fn probe_points(results: &[ProbeResult]) -> Points {
+    let mut points: Points = primary_ping_ms(results).map(|ms| ("ping_ms".to_string(), ms)).into_iter().collect();
+
+    for result in results {
+        let key = format!("{}:{}", result.kind, result.target);
+        points.push((format!("probe_loss_percentage:{}", key), result.loss_percentage));
+        if let Some(avg) = result.avg_ms {
+            points.push((format!("probe_avg_ms:{}", key), avg));
+        }
+        if let Some(jitter) = result.jitter_ms {
+            points.push((format!("probe_jitter_ms:{}", key), jitter));
+        }
+    }
+    points
+}
+
+/// `ping_ms` predates multiple targets; it reports the first ICMP target.
+fn primary_ping_ms(results: &[ProbeResult]) -> Option<f64> {
+    results.iter().find(|r| r.kind == "icmp")?.avg_ms
+}
+
+fn interface_points(interfaces: &[NetworkInterface]) -> Points {
+    let mut points = Vec::new();
+    for iface in interfaces {
+        points.push((format!("net_rx_bytes_per_sec:{}", iface.name), iface.rx_bytes_per_sec));
+        points.push((format!("net_tx_bytes_per_sec:{}", iface.name), iface.tx_bytes_per_sec));
+    }
+    points
+}
+
+fn disk_points(filesystems: &[Filesystem]) -> Points {
+    let mut points = Vec::new();
+    for fs in filesystems {
+        points.push((format!("disk_used_percentage:{}", fs.mount_point), fs.used_percentage));
+        if let Some(inodes) = fs.inodes_used_percentage {
+            points.push((format!("disk_inodes_used_percentage:{}", fs.mount_point), inodes));
+        }
+    }
+    points
+}
+
+fn disk_io_points(devices: &[DiskIo]) -> Points {
+    let mut points = Vec::new();
+    for io in devices {
+        points.push((format!("disk_read_bytes_per_sec:{}", io.device), io.read_bytes_per_sec));
+        points.push((format!("disk_write_bytes_per_sec:{}", io.device), io.write_bytes_per_sec));
+        points.push((format!("disk_await_ms:{}", io.device), io.await_ms));
+        points.push((format!("disk_utilization_percentage:{}", io.device), io.utilization_percentage));
+    }
+    points
+}
+
+impl Snapshot {
+    pub fn status(&self, server_data: &ServerData) -> StatusResponse {
+        let uptime_secs = System::uptime();
+        let uptime = format!(
+            "{}h {}m {}s",
+            uptime_secs / 3600,
+            (uptime_secs % 3600) / 60,
+            uptime_secs % 60
+        );
+
+        let usage = self.usage.as_ref().map(|s| s.value.clone()).unwrap_or_default();
+
+        let temps = match &self.sensors {
+            Some(sample) => TempData { sampled_at: Some(sample.timestamp()), ..sample.value.temps.clone() },
+            None => TempData::default(),
+        };
+
+        StatusResponse {
+            server_status: "online".to_string(),
+            server_uptime: uptime,
+            server_data: server_data.clone(),
+            data: UsageData {
+                cpu_percentage: usage.cpu_percentage,
+                memory: usage.memory_gib(),
+                total_memory: usage.total_memory_gib(),
+                memory_percentage: usage.memory_percentage(),
+                cpu_per_core: usage.cpu_per_core,
+                cpu_average: usage.cpu_average,
+                temps,
+                sampled_at: self.usage.as_ref().map(Sampled::timestamp),
+            },
+            network: NetworkData {
+                public_ip: self.public_ip.as_ref().and_then(|s| s.value.primary()),
+                public_ipv4: self.public_ip.as_ref().and_then(|s| s.value.ipv4.clone()),
+                public_ipv6: self.public_ip.as_ref().and_then(|s| s.value.ipv6.clone()),
+                ping_ms: self.probes.as_ref().and_then(|s| primary_ping_ms(&s.value)),
+                probes: self.probes.as_ref().map(|s| s.value.clone()).unwrap_or_default(),
+                speed_download_mbps: self.speedtest.as_ref().map(|s| s.value.download_mbps),
+                speed_upload_mbps: self.speedtest.as_ref().map(|s| s.value.upload_mbps),
+                speed_latency_ms: self.speedtest.as_ref().and_then(|s| s.value.latency_ms),
+                interfaces: self.interfaces.as_ref().map(|s| s.value.clone()).unwrap_or_default(),
+                sampled_at: NetworkTimestamps {
+                    public_ip: self.public_ip.as_ref().map(Sampled::timestamp),
+                    probes: self.probes.as_ref().map(Sampled::timestamp),
+                    speedtest: self.speedtest.as_ref().map(Sampled::timestamp),
+                    interfaces: self.interfaces.as_ref().map(Sampled::timestamp),
+                },
+            },
+            disks: DiskData {
+                filesystems: self.disks.as_ref().map(|s| s.value.clone()).unwrap_or_default(),
+                io: self.disk_io.as_ref().map(|s| s.value.clone()).unwrap_or_default(),
+                sampled_at: DiskTimestamps {
+                    filesystems: self.disks.as_ref().map(Sampled::timestamp),
+                    io: self.disk_io.as_ref().map(Sampled::timestamp),
+                },
+            },
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+use std::env;
+use std::process::Command;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+
+use crate::auth::Section;
+use crate::config::duration_from_env;
+use crate::sampler::{format_timestamp, Sampled, Sampler};
+use crate::throughput::Client;
+
+#[derive(Serialize, Clone, Copy)]
+pub struct SpeedResult {
+    pub download_mbps: f64,
+    pub upload_mbps: f64,
+    pub latency_ms: Option<f64>,
+}
+
+/// How a speed test is measured: `speedtest-cli` against public servers, or
+/// natively against another status_server acting as throughput server.
+pub enum Backend {
+    Cli,
+    Native(Client),
+}
+
+impl Backend {
+    /// Uses `SPEEDTEST_TARGET` (`http(s)://…` or `tcp://host:port`) when set,
+    /// otherwise falls back to `speedtest-cli`.
+    pub fn from_env() -> Self {
+        let Some(target) = env::var("SPEEDTEST_TARGET").ok().filter(|t| !t.is_empty()) else {
+            return Backend::Cli;
+        };
+
+        let token = env::var("SPEEDTEST_TARGET_TOKEN").ok().filter(|t| !t.is_empty());
+        let duration = duration_from_env("SPEEDTEST_DURATION", "10s");
+        let timeout = duration_from_env("SPEEDTEST_TIMEOUT", "10s");
+        match Client::new(&target, token, duration, timeout) {
+            Ok(client) => Backend::Native(client),
+            Err(e) => {
+                eprintln!("Invalid SPEEDTEST_TARGET ({}), using speedtest-cli", e);
+                Backend::Cli
+            }
+        }
+    }
+
+    fn run(&self) -> Result<SpeedResult, String> {
+        match self {
+            Backend::Cli => run_speedtest_cli(),
+            Backend::Native(client) => client.measure(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    Schedule,
+    Manual,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub trigger: Trigger,
+    /// Name of the API token that queued a manual job.
+    pub requested_by: Option<String>,
+    pub state: JobState,
+    pub queued_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub result: Option<SpeedResult>,
+    pub error: Option<String>,
+}
+
+impl Job {
+    fn is_pending(&self) -> bool {
+        matches!(self.state, JobState::Queued | JobState::Running)
+    }
+}
+
+#[derive(Serialize)]
+pub struct SpeedtestResponse {
+    /// Most recent successful job, if any.
+    pub latest: Option<Job>,
+    /// Past jobs, newest first.
+    pub jobs: Vec<Job>,
+}
+
+struct Jobs {
+    next_id: u64,
+    limit: usize,
+    jobs: VecDeque<Job>,
+}
+
+/// Runs speed tests one at a time on a background worker, either on a fixed
+/// schedule or on demand, and keeps a bounded history of the jobs.
+#[derive(Clone)]
+pub struct Speedtests {
+    jobs: Arc<RwLock<Jobs>>,
+    sender: Sender<u64>,
+}
+
+impl Speedtests {
+    /// Starts the worker and, unless `interval` is zero, a scheduler that
+    /// queues a test immediately and then once per interval. Successful
+    /// results are published to the snapshot and history through `sampler`.
+    pub fn spawn(sampler: &Sampler, backend: Backend, interval: Duration, history_limit: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<u64>();
+        let speedtests = Speedtests {
+            jobs: Arc::new(RwLock::new(Jobs { next_id: 1, limit: history_limit.max(1), jobs: VecDeque::new() })),
+            sender,
+        };
+
+        let worker = speedtests.clone();
+        let sampler = sampler.clone();
+        thread::Builder::new()
+            .name("speedtest".to_string())
+            .spawn(move || {
+                for id in receiver {
+                    worker.update(id, |job| {
+                        job.state = JobState::Running;
+                        job.started_at = Some(format_timestamp(SystemTime::now()));
+                    });
+
+                    let outcome = backend.run();
+                    let finished_at = SystemTime::now();
+                    worker.update(id, |job| {
+                        job.finished_at = Some(format_timestamp(finished_at));
+                        match &outcome {
+                            Ok(result) => {
+                                job.state = JobState::Succeeded;
+                                job.result = Some(*result);
+                            }
+                            Err(e) => {
+                                job.state = JobState::Failed;
+                                job.error = Some(e.clone());
+                            }
+                        }
+                    });
+
+                    match outcome {
+                        Ok(result) => {
+                            let sample = Sampled { value: result, sampled_at: finished_at };
+                            sampler.publish(sample, &speedtest_points(&result), &[Section::Speedtest], |s, v| s.speedtest = Some(v));
+                        }
+                        Err(e) => eprintln!("Speed test {} failed: {}", id, e),
+                    }
+                }
+            })
+            .expect("failed to spawn speedtest thread");
+
+        if !interval.is_zero() {
+            let scheduler = speedtests.clone();
+            thread::Builder::new()
+                .name("speedtest-schedule".to_string())
+                .spawn(move || loop {
+                    scheduler.trigger(Trigger::Schedule, None);
+                    thread::sleep(interval);
+                })
+                .expect("failed to spawn speedtest scheduler thread");
+        }
+
+        speedtests
+    }
+
+    /// Queues a speed test. If one is already queued or running that job is
+    /// returned instead, so repeated requests don't stack up tests.
+    pub fn trigger(&self, trigger: Trigger, requested_by: Option<&str>) -> Job {
+        let mut jobs = self.jobs.write().unwrap();
+        if let Some(pending) = jobs.jobs.iter().find(|job| job.is_pending()) {
+            return pending.clone();
+        }
+
+        let job = Job {
+            id: jobs.next_id,
+            trigger,
+            requested_by: requested_by.map(str::to_string),
+            state: JobState::Queued,
+            queued_at: format_timestamp(SystemTime::now()),
+            started_at: None,
+            finished_at: None,
+            result: None,
+            error: None,
+        };
+        jobs.next_id += 1;
+        jobs.jobs.push_front(job.clone());
+        let limit = jobs.limit;
+        jobs.jobs.truncate(limit);
+        drop(jobs);
+
+        let _ = self.sender.send(job.id);
+        job
+    }
+
+    pub fn job(&self, id: u64) -> Option<Job> {
+        self.jobs.read().unwrap().jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    pub fn response(&self) -> SpeedtestResponse {
+        let jobs: Vec<Job> = self.jobs.read().unwrap().jobs.iter().cloned().collect();
+        SpeedtestResponse {
+            latest: jobs.iter().find(|job| job.state == JobState::Succeeded).cloned(),
+            jobs,
+        }
+    }
+
+    fn update(&self, id: u64, apply: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.write().unwrap().jobs.iter_mut().find(|job| job.id == id) {
+            apply(job);
+        }
+    }
+}
+
+fn speedtest_points(result: &SpeedResult) -> Vec<(String, f64)> {
+    let mut points = vec![
+        ("speed_download_mbps".to_string(), result.download_mbps),
+        ("speed_upload_mbps".to_string(), result.upload_mbps),
+    ];
+    if let Some(latency) = result.latency_ms {
+        points.push(("speed_latency_ms".to_string(), latency));
+    }
+    points
+}
+
+fn run_speedtest_cli() -> Result<SpeedResult, String> {
+    let output = Command::new("speedtest-cli")
+        .arg("--simple")
+        .output()
+        .map_err(|e| format!("failed to run speedtest-cli: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("speedtest-cli exited with {}: {}", output.status, stderr.trim()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut download = None;
+    let mut upload = None;
+    let mut latency_ms = None;
+
+    for line in stdout.lines() {
+        if line.starts_with("Ping:") {
+            latency_ms = line.split_whitespace().nth(1).and_then(|v| v.parse::<f64>().ok());
+        } else if line.starts_with("Download:") {
+            download = line.split_whitespace().nth(1).and_then(|v| v.parse::<f64>().ok());
+        } else if line.starts_with("Upload:") {
+            upload = line.split_whitespace().nth(1).and_then(|v| v.parse::<f64>().ok());
+        }
+    }
+
+    match (download, upload) {
+        (Some(download_mbps), Some(upload_mbps)) => Ok(SpeedResult { download_mbps, upload_mbps, latency_ms }),
+        _ => Err("could not parse speedtest-cli output".to_string()),
+    }
+}
@@ -0,0 +1,317 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::config::duration_from_env;
+use crate::history::Bucket;
+use crate::sampler::format_timestamp;
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// One resolution level of the on-disk store. Every tier has the same
+/// `(metric, ts, min, max, sum, count)` layout; raw rows simply have a count
+/// of one, which lets queries aggregate any tier the same way.
+struct Tier {
+    table: &'static str,
+    resolution: Option<u64>,
+    retention: Duration,
+}
+
+pub struct Retention {
+    pub raw: Duration,
+    pub minute: Duration,
+    pub hour: Duration,
+}
+
+impl Retention {
+    pub fn from_env() -> Self {
+        Retention {
+            raw: duration_from_env("STORE_RAW_RETENTION", "24h"),
+            minute: duration_from_env("STORE_MINUTE_RETENTION", "30days"),
+            hour: duration_from_env("STORE_HOUR_RETENTION", "365days"),
+        }
+    }
+}
+
+/// SQLite-backed metric store that keeps raw points plus 1-minute and 1-hour
+/// rollups, each pruned to its own retention.
+pub struct Store {
+    conn: Mutex<Connection>,
+    tiers: [Tier; 3],
+}
+
+impl Store {
+    pub fn open(data_dir: &Path, retention: Retention) -> rusqlite::Result<Self> {
+        if let Err(e) = std::fs::create_dir_all(data_dir) {
+            eprintln!("Failed to create data directory {}: {}", data_dir.display(), e);
+        }
+
+        let conn = Connection::open(data_dir.join("metrics.sqlite3"))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::with_connection(conn, retention)
+    }
+
+    fn with_connection(conn: Connection, retention: Retention) -> rusqlite::Result<Self> {
+        let tiers = [
+            Tier { table: "points_raw", resolution: None, retention: retention.raw },
+            Tier { table: "points_1m", resolution: Some(60), retention: retention.minute },
+            Tier { table: "points_1h", resolution: Some(3600), retention: retention.hour },
+        ];
+
+        for tier in &tiers {
+            let key = if tier.resolution.is_some() { ", PRIMARY KEY (metric, ts)" } else { "" };
+            conn.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    metric TEXT NOT NULL,
+                    ts REAL NOT NULL,
+                    min REAL NOT NULL,
+                    max REAL NOT NULL,
+                    sum REAL NOT NULL,
+                    count INTEGER NOT NULL{key}
+                );
+                CREATE INDEX IF NOT EXISTS {table}_metric_ts ON {table} (metric, ts);",
+                table = tier.table,
+                key = key,
+            ))?;
+        }
+
+        Ok(Store { conn: Mutex::new(conn), tiers })
+    }
+
+    /// Writes one sample's points to the raw tier and folds them into every
+    /// rollup bucket they fall in.
+    pub fn record(&self, at: SystemTime, points: &[(String, f64)]) -> rusqlite::Result<()> {
+        let ts = unix_secs(at);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        for tier in &self.tiers {
+            let (sql, bucket) = match tier.resolution {
+                None => (
+                    format!("INSERT INTO {} (metric, ts, min, max, sum, count) VALUES (?1, ?2, ?3, ?3, ?3, 1)", tier.table),
+                    ts,
+                ),
+                Some(resolution) => (
+                    format!(
+                        "INSERT INTO {} (metric, ts, min, max, sum, count) VALUES (?1, ?2, ?3, ?3, ?3, 1)
+                         ON CONFLICT (metric, ts) DO UPDATE SET
+                            min = MIN(min, excluded.min),
+                            max = MAX(max, excluded.max),
+                            sum = sum + excluded.sum,
+                            count = count + 1",
+                        tier.table
+                    ),
+                    (ts / resolution as f64).floor() * resolution as f64,
+                ),
+            };
+
+            let mut stmt = tx.prepare_cached(&sql)?;
+            for (metric, value) in points {
+                stmt.execute(params![metric, bucket, value])?;
+            }
+        }
+
+        tx.commit()
+    }
+
+    pub fn prune(&self) -> rusqlite::Result<usize> {
+        let now = unix_secs(SystemTime::now());
+        let conn = self.conn.lock().unwrap();
+        let mut removed = 0;
+
+        for tier in &self.tiers {
+            removed += conn.execute(
+                &format!("DELETE FROM {} WHERE ts < ?1", tier.table),
+                params![now - tier.retention.as_secs_f64()],
+            )?;
+        }
+
+        Ok(removed)
+    }
+
+    pub fn metrics(&self) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let union = self.tiers.iter()
+            .map(|t| format!("SELECT DISTINCT metric FROM {}", t.table))
+            .collect::<Vec<_>>()
+            .join(" UNION ");
+
+        let mut stmt = conn.prepare(&format!("{} ORDER BY metric", union))?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Downsamples `metric` from the finest tier whose retention reaches back
+    /// to `from`. Returns `None` if the metric isn't stored in that tier at all.
+    pub fn query(&self, metric: &str, from: SystemTime, step: Duration) -> rusqlite::Result<Option<Vec<Bucket>>> {
+        let since = SystemTime::now().duration_since(from).unwrap_or_default();
+        let tier = self.tiers.iter()
+            .find(|t| t.retention >= since)
+            .unwrap_or(&self.tiers[self.tiers.len() - 1]);
+        let from = unix_secs(from);
+        let conn = self.conn.lock().unwrap();
+
+        let known = conn
+            .query_row(
+                &format!("SELECT 1 FROM {} WHERE metric = ?1 LIMIT 1", tier.table),
+                params![metric],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if known.is_none() {
+            return Ok(None);
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT CAST(ts / ?1 AS INTEGER) * ?1 AS bucket, MIN(min), SUM(sum) / SUM(count), MAX(max), SUM(count)
+             FROM {} WHERE metric = ?2 AND ts >= ?3
+             GROUP BY bucket ORDER BY bucket",
+            tier.table
+        ))?;
+
+        let rows = stmt.query_map(params![step.as_secs_f64(), metric, from], |row| {
+            let start: f64 = row.get(0)?;
+            Ok(Bucket {
+                timestamp: format_timestamp(UNIX_EPOCH + Duration::from_secs_f64(start)),
+                min: row.get(1)?,
+                avg: row.get(2)?,
+                max: row.get(3)?,
+                count: row.get::<_, i64>(4)? as usize,
+            })
+        })?;
+
+        rows.collect::<rusqlite::Result<Vec<_>>>().map(Some)
+    }
+}
+
+pub fn spawn_pruner(store: &Arc<Store>) {
+    let store = Arc::clone(store);
+
+    thread::Builder::new()
+        .name("store-pruner".to_string())
+        .spawn(move || loop {
+            if let Err(e) = store.prune() {
+                eprintln!("Failed to prune metric store: {}", e);
+            }
+            thread::sleep(PRUNE_INTERVAL);
+        })
+        .expect("failed to spawn store pruner thread");
+}
+
+fn unix_secs(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600;
+    /// An hour boundary, so the rollup buckets below are easy to predict.
+    const T0: u64 = 472_222 * HOUR;
+
+    fn store(raw: u64, minute: u64, hour: u64) -> Store {
+        let retention = Retention {
+            raw: Duration::from_secs(raw),
+            minute: Duration::from_secs(minute),
+            hour: Duration::from_secs(hour),
+        };
+        Store::with_connection(Connection::open_in_memory().unwrap(), retention).unwrap()
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn rows(store: &Store, table: &str) -> Vec<(f64, f64, f64, f64, i64)> {
+        let conn = store.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!("SELECT ts, min, max, sum, count FROM {} ORDER BY ts", table))
+            .unwrap();
+        stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    fn point(value: f64) -> Vec<(String, f64)> {
+        vec![("cpu_temp".to_string(), value)]
+    }
+
+    #[test]
+    fn record_rolls_up_into_every_tier() {
+        let store = store(HOUR, HOUR, HOUR);
+        store.record(at(T0), &point(40.0)).unwrap();
+        store.record(at(T0 + 30), &point(50.0)).unwrap();
+        store.record(at(T0 + 90), &point(45.0)).unwrap();
+
+        assert_eq!(rows(&store, "points_raw").len(), 3);
+        assert_eq!(rows(&store, "points_1m"), [
+            (T0 as f64, 40.0, 50.0, 90.0, 2),
+            ((T0 + 60) as f64, 45.0, 45.0, 45.0, 1),
+        ]);
+        assert_eq!(rows(&store, "points_1h"), [(T0 as f64, 40.0, 50.0, 135.0, 3)]);
+    }
+
+    #[test]
+    fn query_reads_the_finest_tier_that_reaches_back_far_enough() {
+        let store = store(HOUR, 24 * HOUR, 365 * 24 * HOUR);
+        let now = SystemTime::now();
+        let ts = unix_secs(now) - 10.0;
+        {
+            // A different value per tier shows which one answered.
+            let conn = store.conn.lock().unwrap();
+            for (table, value) in [("points_raw", 1.0), ("points_1m", 2.0), ("points_1h", 3.0)] {
+                conn.execute(
+                    &format!("INSERT INTO {} VALUES ('cpu_temp', ?1, ?2, ?2, ?2, 1)", table),
+                    params![ts, value],
+                )
+                .unwrap();
+            }
+        }
+
+        let avg = |since: u64| {
+            let buckets = store.query("cpu_temp", now - Duration::from_secs(since), Duration::from_secs(60));
+            buckets.unwrap().unwrap()[0].avg
+        };
+        assert_eq!(avg(30 * 60), 1.0);
+        assert_eq!(avg(2 * HOUR), 2.0);
+        assert_eq!(avg(7 * 24 * HOUR), 3.0);
+        // Beyond every retention the coarsest tier is still used.
+        assert_eq!(avg(1000 * 24 * HOUR), 3.0);
+    }
+
+    #[test]
+    fn query_downsamples_and_skips_unknown_metrics() {
+        let forever = 100 * 365 * 24 * HOUR;
+        let store = store(forever, forever, forever);
+        for (offset, value) in [(0, 40.0), (30, 50.0), (90, 45.0)] {
+            store.record(at(T0 + offset), &point(value)).unwrap();
+        }
+
+        let buckets = store.query("cpu_temp", at(T0), Duration::from_secs(60)).unwrap().unwrap();
+        let summary: Vec<_> = buckets.iter().map(|b| (b.min, b.avg, b.max, b.count)).collect();
+        assert_eq!(summary, [(40.0, 45.0, 50.0, 2), (45.0, 45.0, 45.0, 1)]);
+        assert_eq!(buckets[0].timestamp, format_timestamp(at(T0)));
+
+        assert!(store.query("gpu_temp", at(T0), Duration::from_secs(60)).unwrap().is_none());
+        assert_eq!(store.metrics().unwrap(), ["cpu_temp"]);
+    }
+
+    #[test]
+    fn prune_applies_each_tier_retention() {
+        let store = store(HOUR, 24 * HOUR, 365 * 24 * HOUR);
+        let now = SystemTime::now();
+        store.record(now - Duration::from_secs(2 * HOUR), &point(40.0)).unwrap();
+        store.record(now - Duration::from_secs(60), &point(50.0)).unwrap();
+
+        assert_eq!(store.prune().unwrap(), 1);
+        assert_eq!(rows(&store, "points_raw").len(), 1);
+        assert_eq!(rows(&store, "points_1m").len(), 2);
+        assert!(!rows(&store, "points_1h").is_empty());
+    }
+}
@@ -0,0 +1,378 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use actix_web::web::Bytes;
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{Scope, Tokens};
+use crate::speedtest::SpeedResult;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+static ZEROS: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+
+/// Upper bound on how long a client may ask the server to send or receive.
+pub const MAX_DURATION: Duration = Duration::from_secs(60);
+const PING_ROUNDS: u32 = 5;
+/// Limits for the unauthenticated part of a raw TCP connection: the header
+/// line must arrive within `HEADER_TIMEOUT` and fit in `MAX_HEADER` bytes.
+const MAX_HEADER: u64 = 512;
+const HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+/// Raw TCP connections served at once; further ones are turned away.
+const MAX_CONNECTIONS: usize = 16;
+
+/// Reply to an upload, so the client can compute throughput from what the
+/// server actually received rather than what it managed to buffer locally.
+#[derive(Serialize, Deserialize)]
+pub struct UploadReport {
+    pub bytes: u64,
+    pub seconds: f64,
+}
+
+/// Body of `GET /throughput/download`: zero-filled chunks until `duration`
+/// has passed.
+pub fn download_stream(duration: Duration) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let deadline = Instant::now() + duration.min(MAX_DURATION);
+    stream::unfold(deadline, |deadline| async move {
+        (Instant::now() < deadline).then(|| (Ok(Bytes::from_static(&ZEROS)), deadline))
+    })
+}
+
+/// Serves the raw TCP variant of the test. Each connection starts with one
+/// header line, `<COMMAND> [args] <token>`, answered by `OK` or `ERR <reason>`:
+///
+/// - `PING <token>` echoes every byte back until the client disconnects.
+/// - `DOWNLOAD <millis> <token>` streams zeros for the given time, then closes.
+/// - `UPLOAD <token>` reads until the client shuts down its write half, then
+///   replies with `<bytes> <seconds>`.
+pub fn spawn_tcp_server(listen: &str, tokens: Arc<Tokens>) -> io::Result<()> {
+    serve(TcpListener::bind(listen)?, tokens)
+}
+
+fn serve(listener: TcpListener, tokens: Arc<Tokens>) -> io::Result<()> {
+    let active = Arc::new(AtomicUsize::new(0));
+
+    thread::Builder::new()
+        .name("throughput-tcp".to_string())
+        .spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let Some(slot) = ConnectionSlot::acquire(&active) else {
+                    let _ = stream.write_all(b"ERR busy\n");
+                    continue;
+                };
+                let tokens = Arc::clone(&tokens);
+                thread::spawn(move || {
+                    let _slot = slot;
+                    if let Err(e) = serve_connection(stream, &tokens) {
+                        eprintln!("Throughput test connection failed: {}", e);
+                    }
+                });
+            }
+        })?;
+
+    Ok(())
+}
+
+/// One of the `MAX_CONNECTIONS` slots, released when the connection ends.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn acquire(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < MAX_CONNECTIONS).then_some(n + 1))
+            .ok()
+            .map(|_| ConnectionSlot(Arc::clone(active)))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn serve_connection(stream: TcpStream, tokens: &Tokens) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(HEADER_TIMEOUT))?;
+    stream.set_write_timeout(Some(HEADER_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let mut header = String::new();
+    reader.by_ref().take(MAX_HEADER).read_line(&mut header)?;
+    if !header.ends_with('\n') {
+        return writer.write_all(b"ERR header too long\n");
+    }
+    let mut parts: Vec<&str> = header.split_whitespace().collect();
+    let token = parts.pop().unwrap_or_default();
+
+    if tokens.verify(token, Scope::TriggerJobs).is_err() {
+        return writer.write_all(b"ERR unauthorized\n");
+    }
+    reader.get_ref().set_read_timeout(Some(MAX_DURATION))?;
+    writer.set_write_timeout(Some(MAX_DURATION))?;
+
+    match parts.as_slice() {
+        ["PING"] => {
+            writer.write_all(b"OK\n")?;
+            let mut byte = [0u8; 1];
+            while reader.read(&mut byte)? == 1 {
+                writer.write_all(&byte)?;
+            }
+            Ok(())
+        }
+        ["DOWNLOAD", millis] => {
+            let Ok(millis) = millis.parse::<u64>() else {
+                return writer.write_all(b"ERR invalid duration\n");
+            };
+            writer.write_all(b"OK\n")?;
+            let deadline = Instant::now() + Duration::from_millis(millis).min(MAX_DURATION);
+            // A client that stops reading can only stall each write until the
+            // deadline, so it can't hold the connection slot any longer.
+            while let Some(remaining) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+                writer.set_write_timeout(Some(remaining))?;
+                match writer.write_all(&ZEROS) {
+                    Ok(()) => {}
+                    Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+        ["UPLOAD"] => {
+            writer.write_all(b"OK\n")?;
+            let started = Instant::now();
+            let deadline = started + MAX_DURATION;
+            let mut buf = vec![0u8; CHUNK_SIZE];
+            let mut bytes = 0u64;
+            // Stops at MAX_DURATION even if the client keeps sending.
+            while let Some(remaining) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+                reader.get_ref().set_read_timeout(Some(remaining))?;
+                match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => bytes += n as u64,
+                    Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            writeln!(writer, "{} {}", bytes, started.elapsed().as_secs_f64())
+        }
+        _ => writer.write_all(b"ERR unknown command\n"),
+    }
+}
+
+/// Where a native speed test connects: another status_server's HTTP API or
+/// its raw TCP listener.
+pub enum Target {
+    Http(String),
+    Tcp(String),
+}
+
+pub struct Client {
+    pub target: Target,
+    pub token: Option<String>,
+    /// How long each direction is measured for.
+    pub duration: Duration,
+    pub timeout: Duration,
+}
+
+impl Client {
+    /// Parses `http(s)://host[:port]` or `tcp://host:port`.
+    pub fn new(target: &str, token: Option<String>, duration: Duration, timeout: Duration) -> Result<Self, String> {
+        let target = if let Some(addr) = target.strip_prefix("tcp://") {
+            Target::Tcp(addr.trim_end_matches('/').to_string())
+        } else if target.starts_with("http://") || target.starts_with("https://") {
+            Target::Http(target.trim_end_matches('/').to_string())
+        } else {
+            return Err(format!("unsupported speed test target {:?}", target));
+        };
+
+        Ok(Client { target, token, duration, timeout })
+    }
+
+    pub fn measure(&self) -> Result<SpeedResult, String> {
+        match &self.target {
+            Target::Http(base) => self.measure_http(base),
+            Target::Tcp(addr) => self.measure_tcp(addr),
+        }
+    }
+
+    fn measure_http(&self, base: &str) -> Result<SpeedResult, String> {
+        let agent = ureq::AgentBuilder::new().timeout_connect(self.timeout).timeout_read(self.timeout).build();
+        let authorize = |request: ureq::Request| match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        };
+
+        // The first request opens the pooled connection, later ones reuse it.
+        let ping = || authorize(agent.get(&format!("{}/throughput/ping", base))).call().map_err(|e| e.to_string());
+        ping()?;
+        let mut rtts = Vec::new();
+        for _ in 0..PING_ROUNDS {
+            let start = Instant::now();
+            ping()?;
+            rtts.push(start.elapsed().as_secs_f64() * 1000.0);
+        }
+
+        let url = format!("{}/throughput/download?duration_ms={}", base, self.duration.as_millis());
+        let response = authorize(agent.get(&url)).call().map_err(|e| e.to_string())?;
+        let start = Instant::now();
+        let bytes = io::copy(&mut response.into_reader(), &mut io::sink()).map_err(|e| e.to_string())?;
+        let download_mbps = mbps(bytes, start.elapsed().as_secs_f64());
+
+        let body = ZeroReader { deadline: Instant::now() + self.duration };
+        let report: UploadReport = authorize(agent.post(&format!("{}/throughput/upload", base)))
+            .send(body)
+            .map_err(|e| e.to_string())?
+            .into_string()
+            .map_err(|e| e.to_string())
+            .and_then(|body| serde_json::from_str(&body).map_err(|e| e.to_string()))?;
+
+        Ok(SpeedResult {
+            download_mbps,
+            upload_mbps: mbps(report.bytes, report.seconds),
+            latency_ms: Some(rtts.iter().sum::<f64>() / rtts.len() as f64),
+        })
+    }
+
+    fn measure_tcp(&self, addr: &str) -> Result<SpeedResult, String> {
+        let (mut ping, _) = self.open(addr, "PING")?;
+        let mut byte = [0u8; 1];
+        let mut rtts = Vec::new();
+        for _ in 0..PING_ROUNDS {
+            let start = Instant::now();
+            ping.write_all(&byte).map_err(|e| e.to_string())?;
+            ping.read_exact(&mut byte).map_err(|e| e.to_string())?;
+            rtts.push(start.elapsed().as_secs_f64() * 1000.0);
+        }
+        drop(ping);
+
+        let (_, mut download) = self.open(addr, &format!("DOWNLOAD {}", self.duration.as_millis()))?;
+        let start = Instant::now();
+        let bytes = io::copy(&mut download, &mut io::sink()).map_err(|e| e.to_string())?;
+        let download_mbps = mbps(bytes, start.elapsed().as_secs_f64());
+
+        let (mut upload, mut reply) = self.open(addr, "UPLOAD")?;
+        io::copy(&mut ZeroReader { deadline: Instant::now() + self.duration }, &mut upload)
+            .map_err(|e| e.to_string())?;
+        upload.shutdown(Shutdown::Write).map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        reply.read_line(&mut line).map_err(|e| e.to_string())?;
+        let (bytes, seconds) = line
+            .split_once(' ')
+            .and_then(|(b, s)| Some((b.parse::<u64>().ok()?, s.trim().parse::<f64>().ok()?)))
+            .ok_or_else(|| format!("unexpected upload reply {:?}", line.trim()))?;
+
+        Ok(SpeedResult {
+            download_mbps,
+            upload_mbps: mbps(bytes, seconds),
+            latency_ms: Some(rtts.iter().sum::<f64>() / rtts.len() as f64),
+        })
+    }
+
+    /// Connects and sends the header line; returns the write half and a
+    /// buffered read half positioned after the server's `OK`.
+    fn open(&self, addr: &str, command: &str) -> Result<(TcpStream, BufReader<TcpStream>), String> {
+        let socket_addr = addr
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("{} did not resolve", addr))?;
+        let mut stream = TcpStream::connect_timeout(&socket_addr, self.timeout).map_err(|e| e.to_string())?;
+        stream.set_nodelay(true).map_err(|e| e.to_string())?;
+        stream.set_read_timeout(Some(self.timeout + self.duration)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(self.timeout)).map_err(|e| e.to_string())?;
+
+        writeln!(stream, "{} {}", command, self.token.as_deref().unwrap_or("-")).map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+        let mut status = String::new();
+        reader.read_line(&mut status).map_err(|e| e.to_string())?;
+        match status.trim() {
+            "OK" => Ok((stream, reader)),
+            other => Err(format!("server refused {}: {}", command.split(' ').next().unwrap_or(command), other)),
+        }
+    }
+}
+
+/// Produces zeros until `deadline`, then reports end of input.
+struct ZeroReader {
+    deadline: Instant,
+}
+
+impl Read for ZeroReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if Instant::now() >= self.deadline {
+            return Ok(0);
+        }
+        let n = buf.len().min(CHUNK_SIZE);
+        buf[..n].fill(0);
+        Ok(n)
+    }
+}
+
+fn mbps(bytes: u64, seconds: f64) -> f64 {
+    if seconds > 0.0 { bytes as f64 * 8.0 / seconds / 1_000_000.0 } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::TokenHash;
+    use std::sync::mpsc;
+
+    const TOKEN: &str = "throughput-test-token";
+
+    fn tokens() -> Arc<Tokens> {
+        Arc::new(Tokens::new(Some(TokenHash::new(TOKEN)), None, Vec::new(), Vec::new()))
+    }
+
+    fn server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        serve(listener, tokens()).unwrap();
+        format!("tcp://{}", addr)
+    }
+
+    fn client(target: &str, token: &str) -> Client {
+        Client::new(target, Some(token.to_string()), Duration::from_millis(200), Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn tcp_client_measures_against_the_server() {
+        let result = client(&server(), TOKEN).measure().unwrap();
+
+        assert!(result.download_mbps > 0.0, "download {}", result.download_mbps);
+        assert!(result.upload_mbps > 0.0, "upload {}", result.upload_mbps);
+        assert!(result.latency_ms.is_some_and(|ms| ms >= 0.0));
+    }
+
+    #[test]
+    fn tcp_server_refuses_bad_tokens_and_commands() {
+        let target = server();
+        assert_eq!(client(&target, "wrong").measure().err().as_deref(), Some("server refused PING: ERR unauthorized"));
+
+        let error = client(&target, TOKEN).open(target.trim_start_matches("tcp://"), "DOWNLOAD soon").err();
+        assert_eq!(error.as_deref(), Some("server refused DOWNLOAD: ERR invalid duration"));
+    }
+
+    #[test]
+    fn download_gives_up_on_a_client_that_stops_reading() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stalled = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        stalled.set_nodelay(true).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        let (done, finished) = mpsc::channel();
+        let tokens = tokens();
+        thread::spawn(move || done.send(serve_connection(stream, &tokens).map_err(|e| e.to_string())));
+        writeln!(stalled, "DOWNLOAD 300 {}", TOKEN).unwrap();
+
+        // Without a write timeout the server would block on a full socket buffer forever.
+        let served = finished.recv_timeout(Duration::from_secs(10)).expect("server still writing");
+        assert_eq!(served, Ok(()));
+    }
+}
@@ -0,0 +1,320 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use rustls::crypto::ring::{default_provider, sign};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
+use rustls::{RootCertStore, ServerConfig, SupportedProtocolVersion};
+
+use crate::config::duration_from_env;
+
+/// HTTPS settings from `TLS_CERT_FILE` and `TLS_KEY_FILE`.
+pub struct TlsSettings {
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+    pub min_version: &'static SupportedProtocolVersion,
+    /// How often the files are checked for changes.
+    pub reload_interval: Duration,
+    /// CA bundle from `TLS_CLIENT_CA_FILE` that client certificates must
+    /// chain to.
+    pub client_ca_file: Option<PathBuf>,
+    /// With `TLS_CLIENT_AUTH=required` connections without a valid client
+    /// certificate are refused during the handshake; otherwise they may
+    /// still authenticate with a bearer token.
+    pub client_auth_required: bool,
+}
+
+impl TlsSettings {
+    /// Returns `Ok(None)` when TLS isn't configured, and an error when it is
+    /// only half configured or the minimum version is unknown.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |key: &str| env::var(key).ok().filter(|v| !v.is_empty());
+        let (cert_file, key_file) = match (var("TLS_CERT_FILE"), var("TLS_KEY_FILE")) {
+            (None, None) => return Ok(None),
+            (Some(cert), Some(key)) => (PathBuf::from(cert), PathBuf::from(key)),
+            _ => return Err("TLS_CERT_FILE and TLS_KEY_FILE must be set together".to_string()),
+        };
+
+        let min_version = match var("TLS_MIN_VERSION").as_deref().unwrap_or("1.2") {
+            "1.2" => &rustls::version::TLS12,
+            "1.3" => &rustls::version::TLS13,
+            other => return Err(format!("TLS_MIN_VERSION must be 1.2 or 1.3, got {:?}", other)),
+        };
+
+        let client_ca_file = var("TLS_CLIENT_CA_FILE").map(PathBuf::from);
+        let client_auth_required = match var("TLS_CLIENT_AUTH").as_deref().unwrap_or("optional") {
+            "optional" => false,
+            "required" if client_ca_file.is_some() => true,
+            "required" => return Err("TLS_CLIENT_AUTH=required needs TLS_CLIENT_CA_FILE".to_string()),
+            other => return Err(format!("TLS_CLIENT_AUTH must be optional or required, got {:?}", other)),
+        };
+
+        Ok(Some(TlsSettings {
+            cert_file,
+            key_file,
+            min_version,
+            reload_interval: duration_from_env("TLS_RELOAD_INTERVAL", "1m"),
+            client_ca_file,
+            client_auth_required,
+        }))
+    }
+
+    pub fn min_version_str(&self) -> &'static str {
+        if self.min_version == &rustls::version::TLS13 { "1.3" } else { "1.2" }
+    }
+
+    /// Loads the certificate and starts watching it. The returned config
+    /// always serves the most recently loaded certificate, so renewals (e.g.
+    /// by certbot) take effect without a restart.
+    pub fn server_config(&self) -> Result<ServerConfig, String> {
+        let resolver = Arc::new(ReloadingCert::load(&self.cert_file, &self.key_file)?);
+        resolver.watch(self.reload_interval);
+
+        let versions: &[&'static SupportedProtocolVersion] = if self.min_version == &rustls::version::TLS13 {
+            &[&rustls::version::TLS13]
+        } else {
+            &[&rustls::version::TLS13, &rustls::version::TLS12]
+        };
+
+        let builder = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_protocol_versions(versions)
+            .map_err(|e| e.to_string())?;
+        let builder = match &self.client_ca_file {
+            Some(ca_file) => builder.with_client_cert_verifier(self.client_verifier(ca_file)?),
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut config = builder.with_cert_resolver(resolver);
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+
+    /// Accepts client certificates that chain to the CAs in `ca_file`. The
+    /// CA bundle is read once; changing it needs a restart.
+    fn client_verifier(&self, ca_file: &Path) -> Result<Arc<dyn ClientCertVerifier>, String> {
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(ca_file).map_err(|e| format!("{}: {}", ca_file.display(), e))? {
+            let cert = cert.map_err(|e| format!("{}: {}", ca_file.display(), e))?;
+            roots.add(cert).map_err(|e| format!("{}: {}", ca_file.display(), e))?;
+        }
+        if roots.is_empty() {
+            return Err(format!("{}: no certificates found", ca_file.display()));
+        }
+
+        let builder = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), Arc::new(default_provider()));
+        let builder = if self.client_auth_required { builder } else { builder.allow_unauthenticated() };
+        builder.build().map_err(|e| format!("{}: {}", ca_file.display(), e))
+    }
+}
+
+/// Modification times of the certificate and key files.
+type Modified = (Option<SystemTime>, Option<SystemTime>);
+
+struct ReloadingCert {
+    cert_file: PathBuf,
+    key_file: PathBuf,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadingCert {
+    fn load(cert_file: &Path, key_file: &Path) -> Result<Self, String> {
+        Ok(ReloadingCert {
+            cert_file: cert_file.to_path_buf(),
+            key_file: key_file.to_path_buf(),
+            current: RwLock::new(Arc::new(certified_key(cert_file, key_file)?)),
+        })
+    }
+
+    /// Polls the modification times and swaps in the new certificate once
+    /// both files parse. A half-written renewal keeps the old one in place
+    /// until the next check.
+    fn watch(self: &Arc<Self>, interval: Duration) {
+        let cert = Arc::clone(self);
+        let mut loaded = cert.modified();
+
+        thread::Builder::new()
+            .name("tls-reload".to_string())
+            .spawn(move || loop {
+                thread::sleep(interval);
+
+                match cert.reload_if_changed(&mut loaded) {
+                    Ok(true) => println!("🔐 Reloaded TLS certificate from {}", cert.cert_file.display()),
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Failed to reload TLS certificate: {}", e),
+                }
+            })
+            .expect("failed to spawn TLS reload thread");
+    }
+
+    /// Loads the pair if either file changed since `loaded`, returning
+    /// whether the served certificate was replaced. `loaded` only advances on
+    /// success, so a failed pair is retried on the next call.
+    fn reload_if_changed(&self, loaded: &mut Modified) -> Result<bool, String> {
+        let modified = self.modified();
+        if modified == *loaded {
+            return Ok(false);
+        }
+
+        let key = certified_key(&self.cert_file, &self.key_file)?;
+        *self.current.write().unwrap() = Arc::new(key);
+        *loaded = modified;
+        Ok(true)
+    }
+
+    fn modified(&self) -> Modified {
+        let mtime = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+        (mtime(&self.cert_file), mtime(&self.key_file))
+    }
+}
+
+impl std::fmt::Debug for ReloadingCert {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ReloadingCert").field("cert_file", &self.cert_file).finish()
+    }
+}
+
+impl ResolvesServerCert for ReloadingCert {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(Arc::clone(&self.current.read().unwrap()))
+    }
+}
+
+fn certified_key(cert_file: &Path, key_file: &Path) -> Result<CertifiedKey, String> {
+    let chain = CertificateDer::pem_file_iter(cert_file)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("{}: {}", cert_file.display(), e))?;
+    if chain.is_empty() {
+        return Err(format!("{}: no certificates found", cert_file.display()));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_file).map_err(|e| format!("{}: {}", key_file.display(), e))?;
+    let key = sign::any_supported_type(&key).map_err(|e| format!("{}: {}", key_file.display(), e))?;
+
+    let certified = CertifiedKey::new(chain, key);
+    certified.keys_match().map_err(|e| format!("{} does not match {}: {}", key_file.display(), cert_file.display(), e))?;
+    Ok(certified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::process;
+
+    use rcgen::{CertificateParams, KeyPair};
+
+    /// A temporary directory holding the certificate and key files.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let root = env::temp_dir().join(format!("status_server-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Fixture(root)
+        }
+
+        fn cert_file(&self) -> PathBuf {
+            self.0.join("cert.pem")
+        }
+
+        fn key_file(&self) -> PathBuf {
+            self.0.join("key.pem")
+        }
+
+        /// Writes `contents` and moves the mtime forward, so the change is
+        /// seen even on filesystems with coarse timestamps.
+        fn write(&self, path: &Path, contents: &str, generation: u64) {
+            fs::write(path, contents).unwrap();
+            let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + generation);
+            File::options().write(true).open(path).unwrap().set_modified(mtime).unwrap();
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A self-signed certificate and its key, both PEM encoded, plus the DER
+    /// certificate to compare against what is served.
+    fn generate(name: &str) -> (String, String, Vec<u8>) {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()]).unwrap().self_signed(&key).unwrap();
+        (cert.pem(), key.serialize_pem(), cert.der().to_vec())
+    }
+
+    fn served(cert: &ReloadingCert) -> Vec<u8> {
+        cert.current.read().unwrap().cert[0].to_vec()
+    }
+
+    #[test]
+    fn picks_up_a_rotated_pair() {
+        let fixture = Fixture::new("tls-rotate");
+        let (cert_a, key_a, der_a) = generate("a.example");
+        fixture.write(&fixture.cert_file(), &cert_a, 1);
+        fixture.write(&fixture.key_file(), &key_a, 1);
+
+        let cert = ReloadingCert::load(&fixture.cert_file(), &fixture.key_file()).unwrap();
+        let mut loaded = cert.modified();
+        assert_eq!(served(&cert), der_a);
+        assert_eq!(cert.reload_if_changed(&mut loaded), Ok(false));
+
+        let (cert_b, key_b, der_b) = generate("b.example");
+        fixture.write(&fixture.cert_file(), &cert_b, 2);
+        fixture.write(&fixture.key_file(), &key_b, 2);
+
+        assert_eq!(cert.reload_if_changed(&mut loaded), Ok(true));
+        assert_eq!(served(&cert), der_b);
+        assert_eq!(cert.reload_if_changed(&mut loaded), Ok(false));
+    }
+
+    #[test]
+    fn keeps_serving_the_old_pair_until_the_new_one_is_complete() {
+        let fixture = Fixture::new("tls-half-written");
+        let (cert_a, key_a, der_a) = generate("a.example");
+        fixture.write(&fixture.cert_file(), &cert_a, 1);
+        fixture.write(&fixture.key_file(), &key_a, 1);
+        let cert = ReloadingCert::load(&fixture.cert_file(), &fixture.key_file()).unwrap();
+        let mut loaded = cert.modified();
+
+        // The new certificate lands before its key: the pair doesn't match.
+        let (cert_b, key_b, der_b) = generate("b.example");
+        fixture.write(&fixture.cert_file(), &cert_b, 2);
+        let error = cert.reload_if_changed(&mut loaded).unwrap_err();
+        assert!(error.contains("does not match"), "{}", error);
+        assert_eq!(served(&cert), der_a);
+
+        // A truncated key is refused as well.
+        fixture.write(&fixture.key_file(), &key_b[..key_b.len() / 2], 2);
+        assert!(cert.reload_if_changed(&mut loaded).is_err());
+        assert_eq!(served(&cert), der_a);
+
+        // Once both files are complete the next check swaps them in.
+        fixture.write(&fixture.key_file(), &key_b, 3);
+        assert_eq!(cert.reload_if_changed(&mut loaded), Ok(true));
+        assert_eq!(served(&cert), der_b);
+    }
+
+    #[test]
+    fn refuses_an_incomplete_pair_at_startup() {
+        let fixture = Fixture::new("tls-startup");
+        let (cert_a, _, _) = generate("a.example");
+        let (_, key_b, _) = generate("b.example");
+        fixture.write(&fixture.cert_file(), &cert_a, 1);
+        fixture.write(&fixture.key_file(), &key_b, 1);
+        assert!(ReloadingCert::load(&fixture.cert_file(), &fixture.key_file()).is_err());
+
+        fixture.write(&fixture.cert_file(), "", 2);
+        let error = ReloadingCert::load(&fixture.cert_file(), &fixture.key_file()).unwrap_err();
+        assert!(error.contains("no certificates found"), "{}", error);
+    }
+}